@@ -2,21 +2,30 @@ use conrod_core::event::Button;
 use conrod_core::input::{Key, MouseButton};
 use conrod_core::{widget, Colorable, Positionable, Sizeable, Widget};
 
-use super::{ActionOverlay, ImageViewer};
-use crate::data::FileList;
+use super::{ActionOverlay, Filmstrip, ImageViewer, ScrollViewer};
+use crate::data::{FileList, Layout, ScaleMode, ViewSettings};
 use crate::res::Resources;
-use crate::systems::EventSystem;
+use crate::systems::{events, AppEvent, EventSystem, Pages, SlideshowSystem, Thumbnails};
+
+const FILMSTRIP_HEIGHT: f64 = 96.0;
+const SLIDESHOW_PROGRESS_HEIGHT: f64 = 3.0;
 
 widget_ids!(struct Ids {
     background,
     overlay,
     viewer,
+    scroll_viewer,
+    filmstrip,
+    slideshow_progress,
     file_nav,
 });
 
 pub struct State {
     ids: Ids,
     is_overlay_visible: bool,
+    is_filmstrip_visible: bool,
+    /// Hides the overlay while fullscreen without forgetting whether it was open.
+    is_idle: bool,
 }
 
 #[derive(WidgetCommon)]
@@ -26,6 +35,10 @@ pub struct App<'a> {
     res: &'a Resources,
     events: &'a mut EventSystem,
     files: &'a Option<FileList>,
+    thumbnails: &'a Thumbnails,
+    pages: &'a Pages,
+    view: &'a ViewSettings,
+    slideshow: &'a SlideshowSystem,
 }
 
 impl<'a> App<'a> {
@@ -33,12 +46,20 @@ impl<'a> App<'a> {
         events: &'a mut EventSystem,
         res: &'a Resources,
         files: &'a Option<FileList>,
+        thumbnails: &'a Thumbnails,
+        pages: &'a Pages,
+        view: &'a ViewSettings,
+        slideshow: &'a SlideshowSystem,
     ) -> Self {
         App {
             common: widget::CommonBuilder::default(),
             res,
             events,
             files,
+            thumbnails,
+            pages,
+            view,
+            slideshow,
         }
     }
 }
@@ -52,13 +73,21 @@ impl<'a> Widget for App<'a> {
         State {
             ids: Ids::new(id_gen),
             is_overlay_visible: false,
+            is_filmstrip_visible: true,
+            is_idle: false,
         }
     }
 
     fn style(&self) -> Self::Style {}
 
     fn update(mut self, args: widget::UpdateArgs<Self>) -> Self::Event {
-        let widget::UpdateArgs { state, ui, id, .. } = args;
+        let widget::UpdateArgs {
+            state,
+            ui,
+            id,
+            rect,
+            ..
+        } = args;
 
         widget::Canvas::new()
             .parent(id)
@@ -67,19 +96,72 @@ impl<'a> Widget for App<'a> {
             .wh_of(id)
             .set(state.ids.background, ui);
 
+        for event in self.events.events() {
+            match event {
+                AppEvent::Window(events::Window::Idle(is_idle)) => {
+                    state.update(|s| s.is_idle = *is_idle)
+                }
+                AppEvent::Window(events::Window::Overlay(is_visible)) => {
+                    state.update(|s| s.is_overlay_visible = *is_visible)
+                }
+                _ => (),
+            }
+        }
+
         self.process_input(ui, state, id);
 
         if let Some(files) = &self.files {
-            ImageViewer::new(self.events)
-                .parent(id)
-                .wh_of(id)
-                .set(state.ids.viewer, ui);
+            let viewer_h = if state.is_filmstrip_visible {
+                (rect.h() - FILMSTRIP_HEIGHT).max(0.0)
+            } else {
+                rect.h()
+            };
+            if self.view.layout == Layout::Continuous {
+                ScrollViewer::new(files, self.pages, self.view, self.events)
+                    .parent(id)
+                    .w(rect.w())
+                    .h(viewer_h)
+                    .top_left_of(id)
+                    .set(state.ids.scroll_viewer, ui);
+            } else {
+                ImageViewer::new(self.events, self.view, self.res)
+                    .parent(id)
+                    .w(rect.w())
+                    .h(viewer_h)
+                    .top_left_of(id)
+                    .set(state.ids.viewer, ui);
+            }
+
+            if state.is_filmstrip_visible {
+                Filmstrip::new(files, self.thumbnails, self.res, self.events)
+                    .parent(id)
+                    .w_of(id)
+                    .h(FILMSTRIP_HEIGHT)
+                    .bottom_left_of(id)
+                    .set(state.ids.filmstrip, ui);
+            }
 
-            if state.is_overlay_visible {
-                ActionOverlay::new(&files, self.res, self.events)
+            if self.slideshow.is_running() {
+                let w = rect.w() * self.slideshow.progress();
+                widget::Rectangle::fill_with([w, SLIDESHOW_PROGRESS_HEIGHT], ui.theme.label_color)
                     .parent(id)
-                    .wh_of(id)
-                    .set(state.ids.overlay, ui);
+                    .graphics_for(id)
+                    .top_left_of(id)
+                    .set(state.ids.slideshow_progress, ui);
+            }
+
+            if state.is_overlay_visible && !state.is_idle {
+                ActionOverlay::new(
+                    files,
+                    self.thumbnails,
+                    self.view,
+                    self.slideshow,
+                    self.res,
+                    self.events,
+                )
+                .parent(id)
+                .wh_of(id)
+                .set(state.ids.overlay, ui);
             }
         } else {
             widget::Text::new("Rerun the program with an argument pointing to a directory or file.\nPicking a file from here may be supported in the future.")
@@ -101,21 +183,93 @@ impl<'a> App<'a> {
         state: &mut widget::State<State>,
         id: widget::Id,
     ) {
-        use crate::systems::events::Nav;
+        use crate::systems::events::{Nav, Pan, View, Window};
+
+        let presses = ui
+            .widget_input(id)
+            .presses()
+            .key()
+            .chain(ui.widget_input(state.ids.viewer).presses().key())
+            .chain(ui.widget_input(state.ids.scroll_viewer).presses().key())
+            .chain(ui.widget_input(state.ids.filmstrip).presses().key());
+        // Handled on press rather than release so that holding a key repeats it.
+        for press in presses {
+            let view = match press.key {
+                Key::Plus | Key::Equals | Key::NumPadPlus => View::ZoomIn,
+                Key::Minus | Key::NumPadMinus => View::ZoomOut,
+                Key::D0 | Key::NumPad0 => View::ZoomReset,
+                Key::Left | Key::H => View::Pan(Pan::Left),
+                Key::Right | Key::L => View::Pan(Pan::Right),
+                Key::Up | Key::K => View::Pan(Pan::Up),
+                Key::Down | Key::J => View::Pan(Pan::Down),
+                Key::PageUp => View::Pan(Pan::PageUp),
+                Key::PageDown => View::Pan(Pan::PageDown),
+                _ => continue,
+            };
+            self.events.push(view.into());
+        }
+
+        let double_clicks = ui
+            .widget_input(state.ids.viewer)
+            .events()
+            .chain(ui.widget_input(state.ids.scroll_viewer).events())
+            .filter_map(|event| match event {
+                conrod_core::event::Widget::DoubleClick(click) => Some(click),
+                _ => None,
+            });
+        for click in double_clicks {
+            if click.button == MouseButton::Left {
+                self.events.push(Window::ToggleFullscreen.into());
+            }
+        }
+
         let releases = ui
             .widget_input(id)
             .releases()
-            .chain(ui.widget_input(state.ids.viewer).releases());
+            .chain(ui.widget_input(state.ids.viewer).releases())
+            .chain(ui.widget_input(state.ids.scroll_viewer).releases())
+            .chain(ui.widget_input(state.ids.filmstrip).releases());
         for release in releases {
             match release.button {
-                Button::Keyboard(Key::Space) | Button::Mouse(MouseButton::Middle, _) => {
-                    state.update(|s| s.is_overlay_visible = !s.is_overlay_visible)
+                Button::Keyboard(Key::Space) | Button::Mouse(MouseButton::Middle, _) => self
+                    .events
+                    .push(Window::Overlay(!state.is_overlay_visible).into()),
+                Button::Keyboard(Key::F11) => self.events.push(Window::ToggleFullscreen.into()),
+                Button::Keyboard(Key::Escape) => self.events.push(Window::Fullscreen(false).into()),
+                Button::Keyboard(Key::F) => {
+                    state.update(|s| s.is_filmstrip_visible = !s.is_filmstrip_visible)
+                }
+                Button::Mouse(MouseButton::Button6, _) => self.events.push(Nav::ImageNext.into()),
+                Button::Mouse(MouseButton::X2, _) => self.events.push(Nav::ImagePrev.into()),
+                Button::Keyboard(Key::D1) => self
+                    .events
+                    .push(View::ScaleMode(ScaleMode::ActualSize).into()),
+                Button::Keyboard(Key::D2) => {
+                    self.events.push(View::ScaleMode(ScaleMode::FitAll).into())
+                }
+                Button::Keyboard(Key::D3) => self
+                    .events
+                    .push(View::ScaleMode(ScaleMode::FitWidth).into()),
+                Button::Keyboard(Key::D4) => self
+                    .events
+                    .push(View::ScaleMode(ScaleMode::FitHeight).into()),
+                Button::Keyboard(Key::D5) => {
+                    self.events.push(View::ScaleMode(ScaleMode::Fill).into())
                 }
-                Button::Mouse(MouseButton::Button6, _) | Button::Keyboard(Key::Right) => {
-                    self.events.push(Nav::ImageNext.into())
+                Button::Keyboard(Key::U) => {
+                    self.events.push(View::Upscale(!self.view.upscale).into())
                 }
-                Button::Mouse(MouseButton::X2, _) | Button::Keyboard(Key::Left) => {
-                    self.events.push(Nav::ImagePrev.into())
+                Button::Keyboard(Key::B) => self
+                    .events
+                    .push(View::Background(self.view.next_background()).into()),
+                Button::Keyboard(Key::P) => self
+                    .events
+                    .push(View::Layout(self.view.next_layout()).into()),
+                Button::Keyboard(Key::G) => self
+                    .events
+                    .push(View::PixelGrid(!self.view.pixel_grid).into()),
+                Button::Keyboard(Key::V) => {
+                    self.events.push(View::Lock(!self.view.lock_view).into())
                 }
                 _ => (),
             }
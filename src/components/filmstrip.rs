@@ -0,0 +1,166 @@
+use conrod_core::{color, widget, Positionable, Sizeable, Widget};
+
+use crate::data::FileList;
+use crate::res::Resources;
+use crate::systems::{events as e, AppEvent, EventSystem, ThumbnailState, Thumbnails};
+
+const TILE_PADDING: f64 = 4.0;
+const BADGE_SIZE: f64 = 16.0;
+
+widget_ids!(struct Ids {
+    background,
+    highlight,
+    tiles[],
+    thumbnails[],
+    badge_stars[],
+    badge_labels[],
+});
+
+pub struct State {
+    ids: Ids,
+}
+
+/// A strip of thumbnails centered on the current file.
+#[derive(WidgetCommon)]
+pub struct Filmstrip<'a> {
+    #[conrod(common_builder)]
+    common: widget::CommonBuilder,
+    files: &'a FileList,
+    thumbnails: &'a Thumbnails,
+    res: &'a Resources,
+    events: &'a mut EventSystem,
+}
+
+impl<'a> Filmstrip<'a> {
+    pub fn new(
+        files: &'a FileList,
+        thumbnails: &'a Thumbnails,
+        res: &'a Resources,
+        events: &'a mut EventSystem,
+    ) -> Self {
+        Filmstrip {
+            common: widget::CommonBuilder::default(),
+            files,
+            thumbnails,
+            res,
+            events,
+        }
+    }
+}
+
+impl<'a> Widget for Filmstrip<'a> {
+    type State = State;
+    type Style = ();
+    type Event = ();
+
+    fn init_state(&self, id_gen: widget::id::Generator) -> Self::State {
+        State {
+            ids: Ids::new(id_gen),
+        }
+    }
+
+    fn style(&self) -> Self::Style {}
+
+    fn update(self, args: widget::UpdateArgs<Self>) -> Self::Event {
+        let widget::UpdateArgs {
+            state,
+            ui,
+            id,
+            rect,
+            ..
+        } = args;
+
+        widget::Rectangle::fill_with(rect.dim(), color::BLACK)
+            .parent(id)
+            .graphics_for(id)
+            .xy(rect.xy())
+            .set(state.ids.background, ui);
+
+        let tile = rect.h();
+        if tile <= TILE_PADDING * 2.0 {
+            return;
+        }
+
+        // The current file is always in the center, so the strip scrolls as it changes.
+        let current = self.files.current_index() as isize;
+        let slots = (rect.w() / tile / 2.0).ceil() as isize;
+        let count = (slots * 2 + 1) as usize;
+        if state.ids.tiles.len() != count {
+            state.update(|s| {
+                let mut id_gen = ui.widget_id_generator();
+                s.ids.tiles.resize(count, &mut id_gen);
+                s.ids.thumbnails.resize(count, &mut id_gen);
+                s.ids.badge_stars.resize(count, &mut id_gen);
+                s.ids.badge_labels.resize(count, &mut id_gen);
+            });
+        }
+
+        let inner = tile - TILE_PADDING * 2.0;
+        for slot in 0..count {
+            let offset = slot as isize - slots;
+            let index = current + offset;
+            if index < 0 {
+                continue;
+            }
+            let file = match self.files.get_file(index as usize) {
+                Some(file) => file,
+                None => continue,
+            };
+            let x = offset as f64 * tile;
+
+            widget::Rectangle::fill_with([inner, inner], ui.theme.shape_color)
+                .parent(id)
+                .graphics_for(id)
+                .x_y_relative_to(id, x, 0.0)
+                .set(state.ids.tiles[slot], ui);
+
+            match self.thumbnails.get(&file.path) {
+                Some(ThumbnailState::Loaded(thumb)) => {
+                    let scale = (inner / thumb.w as f64).min(inner / thumb.h as f64);
+                    widget::Image::new(thumb.id)
+                        .parent(id)
+                        .graphics_for(id)
+                        .w_h(thumb.w as f64 * scale, thumb.h as f64 * scale)
+                        .middle_of(state.ids.tiles[slot])
+                        .set(state.ids.thumbnails[slot], ui);
+                }
+                Some(_) => (),
+                None => self.events.push(AppEvent::LoadThumbnail(file.path.clone())),
+            }
+
+            if let Some(rating) = &file.rating {
+                widget::Image::new(self.res.images.star_filled)
+                    .parent(id)
+                    .graphics_for(id)
+                    .w_h(BADGE_SIZE, BADGE_SIZE)
+                    .bottom_left_of(state.ids.tiles[slot])
+                    .set(state.ids.badge_stars[slot], ui);
+                widget::Text::new(&format!("{}", rating.as_i64()))
+                    .parent(id)
+                    .graphics_for(id)
+                    .font_size(ui.theme.font_size_small)
+                    .right_from(state.ids.badge_stars[slot], 2.0)
+                    .align_middle_y_of(state.ids.badge_stars[slot])
+                    .set(state.ids.badge_labels[slot], ui);
+            }
+        }
+
+        widget::Rectangle::outline_styled(
+            [tile - TILE_PADDING, tile - TILE_PADDING],
+            widget::line::Style::solid()
+                .color(color::DARK_RED)
+                .thickness(TILE_PADDING),
+        )
+        .parent(id)
+        .graphics_for(id)
+        .xy(rect.xy())
+        .set(state.ids.highlight, ui);
+
+        for click in ui.widget_input(id).clicks().left() {
+            let index = current + (click.xy[0] / tile).round() as isize;
+            if index >= 0 && (index as usize) < self.files.len() && index != current {
+                self.events.push(e::Nav::ImageIndex(index as usize).into());
+            }
+        }
+    }
+}
@@ -1,7 +1,11 @@
 mod app;
+mod filmstrip;
 mod overlay;
+mod scroll_viewer;
 mod viewer;
 
 pub use self::app::App;
+pub use self::filmstrip::Filmstrip;
 pub use self::overlay::ActionOverlay;
+pub use self::scroll_viewer::ScrollViewer;
 pub use self::viewer::ImageViewer;
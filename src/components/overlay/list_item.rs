@@ -1,14 +1,22 @@
 use chrono::{DateTime, Local};
-use conrod_core::{widget, Colorable, Positionable, Sizeable, Widget};
+use conrod_core::{color, widget, Colorable, Positionable, Sizeable, Widget};
 use std::ffi::OsStr;
 
 use crate::data::File;
+use crate::res::Resources;
+use crate::systems::ThumbnailState;
+
+const STAR_SIZE: f64 = 12.0;
 
 widget_ids!(struct Ids {
     background,
+    thumbnail_placeholder,
+    thumbnail,
     name,
+    name_runs[],
     date,
     size,
+    stars[],
 });
 
 pub struct State {
@@ -26,6 +34,9 @@ pub struct Style {
     /// Size of the item's font
     #[conrod(default = "theme.font_size_small")]
     pub font_size: Option<conrod_core::FontSize>,
+    /// Color of the chars in the name matched by the filter
+    #[conrod(default = "color::ORANGE")]
+    pub highlight_color: Option<conrod_core::Color>,
 }
 
 #[derive(WidgetCommon)]
@@ -34,17 +45,33 @@ pub struct ListItem<'a> {
     common: widget::CommonBuilder,
     style: Style,
     file: &'a File,
+    /// The index of each char in the name to highlight.
+    highlights: Vec<usize>,
+    thumbnail: Option<ThumbnailState>,
+    res: &'a Resources,
 }
 
 impl<'a> ListItem<'a> {
-    pub fn new(file: &File) -> ListItem {
+    pub fn new(
+        file: &'a File,
+        thumbnail: Option<ThumbnailState>,
+        res: &'a Resources,
+    ) -> ListItem<'a> {
         ListItem {
             common: widget::CommonBuilder::default(),
             style: Style::default(),
             file,
+            highlights: Vec::new(),
+            thumbnail,
+            res,
         }
     }
 
+    pub fn with_highlights(mut self, highlights: Vec<usize>) -> Self {
+        self.highlights = highlights;
+        self
+    }
+
     pub fn with_style(mut self, s: Option<Style>) -> Self {
         if let Some(s) = s {
             self.style = s;
@@ -86,43 +113,111 @@ impl<'a> Widget for ListItem<'a> {
             .pad(4.0)
             .set(state.ids.background, ui);
 
+        let [w, h] = ui.wh_of(id).unwrap_or([300.0, 50.0]);
+        let thumb_size = (h - 8.0).max(0.0);
+        widget::Rectangle::fill_with([thumb_size, thumb_size], color::BLACK.alpha(0.3))
+            .parent(id)
+            .graphics_for(id)
+            .top_left_of(state.ids.background)
+            .set(state.ids.thumbnail_placeholder, ui);
+
+        if let Some(ThumbnailState::Loaded(thumb)) = self.thumbnail {
+            let scale = (thumb_size / thumb.w as f64).min(thumb_size / thumb.h as f64);
+            widget::Image::new(thumb.id)
+                .parent(id)
+                .graphics_for(id)
+                .w_h(thumb.w as f64 * scale, thumb.h as f64 * scale)
+                .middle_of(state.ids.thumbnail_placeholder)
+                .set(state.ids.thumbnail, ui);
+        }
+
+        let rating: usize = self.file.rating.clone().map(|r| r.into()).unwrap_or(0);
+        if state.ids.stars.len() != rating {
+            state.update(|s| s.ids.stars.resize(rating, &mut ui.widget_id_generator()));
+        }
+        for i in 0..rating {
+            let star = widget::Image::new(self.res.images.star_filled)
+                .parent(id)
+                .graphics_for(id)
+                .w_h(STAR_SIZE, STAR_SIZE);
+            let star = if i == 0 {
+                star.top_right_of(state.ids.background)
+            } else {
+                let prev = state.ids.stars[i - 1];
+                star.align_top_of(prev).left_from(prev, 0.0)
+            };
+            star.set(state.ids.stars[i], ui);
+        }
+
         // TODO: measure strings and layout correctly
-        let h = self.get_h(ui).unwrap_or(50.0) / 2.5;
+        let text_w = (w - thumb_size - 12.0).max(0.0);
+        let h = h / 2.5;
         let name = self
             .file
             .path
             .file_name()
             .unwrap_or_else(|| OsStr::new(""))
             .to_string_lossy();
-        widget::Text::new(&name)
+        let runs = highlight_runs(&name, &self.highlights);
+        widget::Text::new(if runs.len() > 1 { "" } else { &name })
             .parent(id)
             .graphics_for(id)
-            .w_of(id)
+            .w((text_w - STAR_SIZE * rating as f64).max(0.0))
             .h(h)
-            .top_left_of(state.ids.background)
+            .right_from(state.ids.thumbnail_placeholder, 4.0)
+            .align_top_of(state.ids.thumbnail_placeholder)
             .left_justify()
             .no_line_wrap()
+            .crop_kids()
             .set(state.ids.name, ui);
 
+        // Highlighted names are drawn in runs, each sized to its text and placed after the last.
+        // They're cropped to the name, which leaves room for the stars.
+        if runs.len() > 1 {
+            if state.ids.name_runs.len() != runs.len() {
+                let len = runs.len();
+                state.update(|s| s.ids.name_runs.resize(len, &mut ui.widget_id_generator()));
+            }
+            for (i, (run, is_highlighted)) in runs.iter().enumerate() {
+                let color = if *is_highlighted {
+                    style.highlight_color(&ui.theme)
+                } else {
+                    ui.theme.label_color
+                };
+                let text = widget::Text::new(run)
+                    .parent(state.ids.name)
+                    .graphics_for(id)
+                    .color(color)
+                    .no_line_wrap();
+                let text = if i == 0 {
+                    text.top_left_of(state.ids.name)
+                } else {
+                    let prev = state.ids.name_runs[i - 1];
+                    text.align_top_of(prev).right_from(prev, 0.0)
+                };
+                text.set(state.ids.name_runs[i], ui);
+            }
+        }
+
         let modified: DateTime<Local> = DateTime::from(self.file.last_modified());
-        let modified = modified.format("    %F").to_string();
+        let modified = modified.format("%F").to_string();
         widget::Text::new(&modified)
             .parent(id)
             .graphics_for(id)
-            .w_of(id)
+            .w(text_w)
             .h(h)
-            .bottom_left_of(state.ids.background)
+            .align_bottom_of(state.ids.thumbnail_placeholder)
             .align_left_of(state.ids.name)
             .left_justify()
             .no_line_wrap()
             .set(state.ids.date, ui);
 
         let size = self.file.size();
-        let size = format!("{}    ", size);
+        let size = format!("{}", size);
         widget::Text::new(&size)
             .parent(id)
             .graphics_for(id)
-            .w_of(id)
+            .w(text_w)
             .h(h)
             .align_left_of(state.ids.date)
             .align_top_of(state.ids.date)
@@ -131,3 +226,39 @@ impl<'a> Widget for ListItem<'a> {
             .set(state.ids.size, ui);
     }
 }
+
+/// Splits a name into runs of chars which are all either highlighted or not.
+fn highlight_runs(name: &str, highlights: &[usize]) -> Vec<(String, bool)> {
+    let mut runs: Vec<(String, bool)> = Vec::new();
+    for (i, c) in name.chars().enumerate() {
+        let is_highlighted = highlights.contains(&i);
+        match runs.last_mut() {
+            Some((run, is_run_highlighted)) if *is_run_highlighted == is_highlighted => run.push(c),
+            _ => runs.push((c.to_string(), is_highlighted)),
+        }
+    }
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn highlight_runs_split_at_changes() {
+        let run = |text: &str, is_highlighted| (text.to_owned(), is_highlighted);
+        assert_eq!(
+            highlight_runs("beach.png", &[0, 1, 5]),
+            vec![
+                run("be", true),
+                run("ach", false),
+                run(".", true),
+                run("png", false)
+            ]
+        );
+        assert_eq!(
+            highlight_runs("beach.png", &[]),
+            vec![run("beach.png", false)]
+        );
+    }
+}
@@ -1,9 +1,13 @@
-use conrod_core::{color, widget, Labelable, Positionable, Sizeable, Widget};
-use std::time::Duration;
+use conrod_core::{color, widget, Colorable, Labelable, Positionable, Sizeable, Widget};
+use std::time::{Duration, Instant};
 
-use crate::data::{FileList, Rating, FILE_SORT_METHODS};
+use crate::data::{
+    Background, FileList, Layout, Query, Rating, RatingFilter, SlideshowOrder, Transition,
+    ViewSettings, BACKGROUNDS, FILE_SORT_METHODS, LAYOUTS, NAME_MODES, SCALE_MODES,
+    SLIDESHOW_ORDERS, TRANSITIONS,
+};
 use crate::res::Resources;
-use crate::systems::{events as e, AppEvent, EventSystem};
+use crate::systems::{events as e, AppEvent, EventSystem, SlideshowSystem, Thumbnails};
 
 mod list_item;
 mod rating;
@@ -14,16 +18,56 @@ widget_ids!(struct Ids {
     sort,
     file_list,
     filter_text,
+    filter_count,
+    filter_error,
+    filter_error_bg,
     filter_rating,
+    filter_rating_mode,
+    name_mode,
+    case_sensitive,
+    folding,
+    saved_filter,
+    saved_filter_name,
+    saved_filter_global,
+    save_filter,
+    delete_filter,
     rating,
     bg_list,
     slideshow,
+    slideshow_interval,
+    slideshow_repeat,
+    slideshow_shuffle,
+    slideshow_pause,
+    slideshow_wait,
+    slideshow_order,
+    slideshow_cooldown,
+    rating_weights[],
+    scale_mode,
+    upscale,
+    remember_view,
+    lock_view,
+    pixel_grid,
+    pixel_grid_scale,
+    background,
+    background_color[],
+    layout,
+    right_to_left,
+    single_first_page,
+    transition,
+    transition_duration,
 });
 
 pub struct State {
     ids: Ids,
     filter_text: String,
-    filter_rating: Option<Rating>,
+    /// When the filter text was last edited, until the filter is applied.
+    filter_edited: Option<Instant>,
+    /// Why the typed filter can't be applied, if it is invalid.
+    filter_error: Option<String>,
+    filter_rating: Option<RatingFilter>,
+    /// The name being typed for the current filter, while saving it.
+    saved_filter_name: Option<String>,
+    is_saved_filter_global: bool,
 }
 
 #[derive(WidgetCommon)]
@@ -31,15 +75,28 @@ pub struct ActionOverlay<'a> {
     #[conrod(common_builder)]
     common: widget::CommonBuilder,
     files: &'a FileList,
+    thumbnails: &'a Thumbnails,
+    view: &'a ViewSettings,
+    slideshow: &'a SlideshowSystem,
     res: &'a Resources,
     events: &'a mut EventSystem,
 }
 
 impl<'a> ActionOverlay<'a> {
-    pub fn new(files: &'a FileList, res: &'a Resources, events: &'a mut EventSystem) -> Self {
+    pub fn new(
+        files: &'a FileList,
+        thumbnails: &'a Thumbnails,
+        view: &'a ViewSettings,
+        slideshow: &'a SlideshowSystem,
+        res: &'a Resources,
+        events: &'a mut EventSystem,
+    ) -> Self {
         ActionOverlay {
             common: widget::CommonBuilder::default(),
             files,
+            thumbnails,
+            view,
+            slideshow,
             res,
             events,
         }
@@ -54,8 +111,12 @@ impl<'a> Widget for ActionOverlay<'a> {
     fn init_state(&self, id_gen: widget::id::Generator) -> Self::State {
         State {
             ids: Ids::new(id_gen),
-            filter_text: "".to_owned(),
-            filter_rating: None,
+            filter_text: self.files.filter().name().unwrap_or("").to_owned(),
+            filter_edited: None,
+            filter_error: None,
+            filter_rating: self.files.filter().rating().cloned(),
+            saved_filter_name: None,
+            is_saved_filter_global: false,
         }
     }
 
@@ -77,33 +138,118 @@ impl<'a> Widget for ActionOverlay<'a> {
             .top_right()
             .set(state.ids.bg_list, ui);
 
+        let name_options = self.files.filter().name_options();
         for event in widget::TextBox::new(&state.filter_text)
             .parent(id)
-            .w_h(300.0, ACTION_HEIGHT)
-            .top_right_of(state.ids.bg_list)
+            .w_h(300.0 - FILTER_COUNT_WIDTH, ACTION_HEIGHT)
+            .top_left_of(state.ids.bg_list)
             .set(state.ids.filter_text, ui)
         {
             use conrod_core::widget::text_box::Event;
             match event {
-                Event::Update(str) => state.update(|s| s.filter_text = str),
-                Event::Enter => self
-                    .events
-                    .push(e::Filter::Text(state.filter_text.clone()).into()),
+                Event::Update(str) => state.update(|s| {
+                    s.filter_text = str;
+                    s.filter_edited = Some(Instant::now());
+                }),
+                Event::Enter => match Query::parse(&state.filter_text, name_options) {
+                    Ok(query) => {
+                        self.events
+                            .push(e::Filter::Text(state.filter_text.clone()).into());
+                        // Ranked files put the best match first.
+                        if query.is_ranked() {
+                            self.events.push(e::Nav::ImageIndex(0).into());
+                        }
+                        state.update(|s| {
+                            s.filter_edited = None;
+                            s.filter_error = None;
+                        });
+                    }
+                    Err(error) => state.update(|s| {
+                        s.filter_edited = None;
+                        s.filter_error = Some(error);
+                    }),
+                },
+            }
+        }
+
+        let is_filter_focused = is_capturing_keyboard(ui, state.ids.filter_text);
+        let is_saved_filter_name_focused = is_capturing_keyboard(ui, state.ids.saved_filter_name);
+        let is_escape_pressed = ui.global_input().events().ui().any(|event| {
+            use conrod_core::event::{Button, Press, Ui};
+            use conrod_core::input::Key;
+            matches!(
+                event,
+                Ui::Press(
+                    _,
+                    Press {
+                        button: Button::Keyboard(Key::Escape),
+                        ..
+                    }
+                )
+            )
+        });
+        if is_filter_focused && is_escape_pressed {
+            self.events.push(e::Filter::Text(String::new()).into());
+            state.update(|s| {
+                s.filter_text.clear();
+                s.filter_edited = None;
+                s.filter_error = None;
+            });
+        }
+        if is_saved_filter_name_focused && is_escape_pressed {
+            state.update(|s| s.saved_filter_name = None);
+        }
+
+        // Filter as the user types, once they pause, so each key doesn't refilter the files.
+        if let Some(edited) = state.filter_edited {
+            if edited.elapsed() >= FILTER_DELAY {
+                let error = Query::parse(&state.filter_text, name_options).err();
+                if error.is_none() && self.files.filter().name() != Some(&state.filter_text) {
+                    self.events
+                        .push(e::Filter::Text(state.filter_text.clone()).into());
+                }
+                state.update(|s| {
+                    s.filter_edited = None;
+                    s.filter_error = error;
+                });
             }
         }
 
-        if let Some(filter_rating) = rating::StarRating::new(state.filter_rating.clone(), self.res)
+        let count = format!("{} / {}\nshown", self.files.len(), self.files.total_len());
+        widget::Text::new(&count)
             .parent(id)
-            .w_h(300.0, ACTION_HEIGHT)
+            .graphics_for(state.ids.filter_text)
+            .font_size(12)
+            .center_justify()
+            .w(FILTER_COUNT_WIDTH)
+            .right_from(state.ids.filter_text, 0.0)
+            .align_middle_y_of(state.ids.filter_text)
+            .set(state.ids.filter_count, ui);
+
+        let mut new_rating_filter = state.filter_rating.clone();
+        let selected = match &state.filter_rating {
+            Some(RatingFilter::AtLeast(r)) | Some(RatingFilter::Exactly(r)) => Some(r.clone()),
+            _ => None,
+        };
+        let filled = state.filter_rating.as_ref().and_then(RatingFilter::bounds);
+        if let Some(clicked) = rating::StarRating::new(selected, self.res)
+            .filled(filled)
+            .parent(id)
+            .w_h(300.0 - RATING_MODE_WIDTH, ACTION_HEIGHT)
             .align_left_of(state.ids.filter_text)
             .down_from(state.ids.filter_text, 0.0)
             .set(state.ids.filter_rating, ui)
         {
-            if state.filter_rating != filter_rating {
-                self.events
-                    .push(e::Filter::Rating(filter_rating.clone()).into());
-                state.update(|s| s.filter_rating = filter_rating);
-            }
+            new_rating_filter = match (&state.filter_rating, clicked) {
+                (_, None) => None,
+                (Some(RatingFilter::Exactly(_)), Some(r)) => Some(RatingFilter::Exactly(r)),
+                (Some(RatingFilter::Between(min, max)), Some(r)) => Some(extend_range(
+                    min.clone().into(),
+                    max.clone().into(),
+                    r.into(),
+                )),
+                (_, Some(r)) => Some(RatingFilter::AtLeast(r)),
+            };
         }
 
         let (mut events, scrollbar) = widget::ListSelect::single(self.files.len())
@@ -111,9 +257,10 @@ impl<'a> Widget for ActionOverlay<'a> {
             .flow_down()
             .item_size(50.0)
             .scrollbar_next_to()
-            .w_of(state.ids.filter_text)
-            .h(ui.h_of(id).unwrap_or(ui.win_h) - ACTION_HEIGHT - ACTION_HEIGHT)
-            .down_from(state.ids.filter_rating, 0.0)
+            .w(300.0)
+            .h(ui.h_of(id).unwrap_or(ui.win_h) - ACTION_HEIGHT * 3.0)
+            .align_left_of(state.ids.filter_text)
+            .down_from(state.ids.filter_rating, ACTION_HEIGHT)
             .set(state.ids.file_list, ui);
         while let Some(event) = events.next(ui, |i| self.files.current_index() == i) {
             use conrod_core::widget::list_select::Event;
@@ -127,7 +274,14 @@ impl<'a> Widget for ActionOverlay<'a> {
                         } else {
                             None
                         };
-                        let widget = list_item::ListItem::new(file).with_style(style);
+                        let thumbnail = self.thumbnails.get(&file.path);
+                        if thumbnail.is_none() {
+                            self.events.push(AppEvent::LoadThumbnail(file.path.clone()));
+                        }
+                        let highlights = self.files.filter().highlights(&file.name());
+                        let widget = list_item::ListItem::new(file, thumbnail, self.res)
+                            .with_highlights(highlights)
+                            .with_style(style);
                         item.set(widget, ui);
                     }
                 }
@@ -139,6 +293,170 @@ impl<'a> Widget for ActionOverlay<'a> {
             s.set(ui);
         }
 
+        let mode = rating_filter_mode(&state.filter_rating);
+        if let Some(new_mode) = widget::DropDownList::new(RATING_FILTER_MODES, Some(mode))
+            .parent(id)
+            .right_from(state.ids.filter_rating, 0.0)
+            .align_top_of(state.ids.filter_rating)
+            .w_h(RATING_MODE_WIDTH, ACTION_HEIGHT)
+            .set(state.ids.filter_rating_mode, ui)
+        {
+            if new_mode != mode {
+                new_rating_filter = with_rating_filter_mode(&state.filter_rating, new_mode);
+            }
+        }
+
+        if new_rating_filter != state.filter_rating {
+            self.events
+                .push(e::Filter::Rating(new_rating_filter.clone()).into());
+            state.update(|s| s.filter_rating = new_rating_filter);
+        }
+
+        let saved_filters = self.files.saved_filters();
+        let saved_filter_w = 300.0 - SAVED_FILTER_BUTTON_WIDTH * 2.0;
+        match state.saved_filter_name.clone() {
+            None => {
+                let labels: Vec<_> = saved_filters
+                    .iter()
+                    .map(|(saved, count)| match count {
+                        Some(count) => format!("{} ({})", saved.name, count),
+                        None => saved.name.clone(),
+                    })
+                    .collect();
+                let selected = saved_filters
+                    .iter()
+                    .position(|(saved, _)| saved.filter == *self.files.filter());
+                if let Some(i) = widget::DropDownList::new(&labels, selected)
+                    .parent(id)
+                    .w_h(saved_filter_w, ACTION_HEIGHT)
+                    .align_left_of(state.ids.filter_rating)
+                    .down_from(state.ids.filter_rating, 0.0)
+                    .label("Saved Filters")
+                    .set(state.ids.saved_filter, ui)
+                {
+                    match saved_filters.get(i) {
+                        Some((saved, _)) if Some(i) != selected => {
+                            let filter = &saved.filter;
+                            self.events.push(e::Filter::Apply(filter.clone()).into());
+                            if filter.is_ranked() {
+                                self.events.push(e::Nav::ImageIndex(0).into());
+                            }
+                            state.update(|s| {
+                                s.filter_text = filter.name().unwrap_or("").to_owned();
+                                s.filter_edited = None;
+                                s.filter_error = None;
+                                s.filter_rating = filter.rating().cloned();
+                            });
+                        }
+                        _ => (),
+                    }
+                }
+
+                for _click in widget::Button::new()
+                    .parent(id)
+                    .right_from(state.ids.saved_filter, 0.0)
+                    .align_top_of(state.ids.saved_filter)
+                    .w_h(SAVED_FILTER_BUTTON_WIDTH, ACTION_HEIGHT)
+                    .label("Save")
+                    .set(state.ids.save_filter, ui)
+                {
+                    state.update(|s| s.saved_filter_name = Some(String::new()));
+                }
+
+                for _click in widget::Button::new()
+                    .parent(id)
+                    .right_from(state.ids.save_filter, 0.0)
+                    .align_top_of(state.ids.save_filter)
+                    .w_h(SAVED_FILTER_BUTTON_WIDTH, ACTION_HEIGHT)
+                    .label("Del")
+                    .set(state.ids.delete_filter, ui)
+                {
+                    if let Some((saved, _)) = selected.and_then(|i| saved_filters.get(i)) {
+                        self.events.push(
+                            e::Filter::Delete {
+                                name: saved.name.clone(),
+                                is_global: saved.is_global,
+                            }
+                            .into(),
+                        );
+                    }
+                }
+            }
+            Some(name) => {
+                let mut is_done = false;
+                for event in widget::TextBox::new(&name)
+                    .parent(id)
+                    .w_h(saved_filter_w, ACTION_HEIGHT)
+                    .align_left_of(state.ids.filter_rating)
+                    .down_from(state.ids.filter_rating, 0.0)
+                    .set(state.ids.saved_filter_name, ui)
+                {
+                    use conrod_core::widget::text_box::Event;
+                    match event {
+                        Event::Update(name) => state.update(|s| s.saved_filter_name = Some(name)),
+                        Event::Enter => is_done = true,
+                    }
+                }
+
+                // Saved for every directory, or only this one.
+                let is_global = state.is_saved_filter_global;
+                for is_global in widget::Toggle::new(is_global)
+                    .parent(id)
+                    .right_from(state.ids.saved_filter_name, 0.0)
+                    .align_top_of(state.ids.saved_filter_name)
+                    .w_h(SAVED_FILTER_BUTTON_WIDTH, ACTION_HEIGHT)
+                    .label(if is_global { "All" } else { "Here" })
+                    .set(state.ids.saved_filter_global, ui)
+                {
+                    state.update(|s| s.is_saved_filter_global = is_global);
+                }
+
+                for _click in widget::Button::new()
+                    .parent(id)
+                    .right_from(state.ids.saved_filter_global, 0.0)
+                    .align_top_of(state.ids.saved_filter_global)
+                    .w_h(SAVED_FILTER_BUTTON_WIDTH, ACTION_HEIGHT)
+                    .label("OK")
+                    .set(state.ids.save_filter, ui)
+                {
+                    is_done = true;
+                }
+
+                // Saving without a name cancels.
+                if is_done {
+                    let name = state.saved_filter_name.clone().unwrap_or_default();
+                    if !name.trim().is_empty() {
+                        self.events.push(
+                            e::Filter::Save {
+                                name: name.trim().to_owned(),
+                                is_global: state.is_saved_filter_global,
+                            }
+                            .into(),
+                        );
+                    }
+                    state.update(|s| s.saved_filter_name = None);
+                }
+            }
+        }
+
+        if let Some(error) = &state.filter_error {
+            widget::Rectangle::fill_with([300.0, ACTION_HEIGHT], color::DARK_RED)
+                .parent(id)
+                .graphics_for(state.ids.filter_text)
+                .align_left_of(state.ids.filter_text)
+                .down_from(state.ids.filter_text, 0.0)
+                .set(state.ids.filter_error_bg, ui);
+            widget::Text::new(error)
+                .parent(id)
+                .graphics_for(state.ids.filter_text)
+                .font_size(12)
+                .color(color::WHITE)
+                .wrap_by_word()
+                .padded_w_of(state.ids.filter_error_bg, 6.0)
+                .middle_of(state.ids.filter_error_bg)
+                .set(state.ids.filter_error, ui);
+        }
+
         for _click in widget::Button::new()
             .parent(id)
             .left_from(state.ids.filter_text, 0.0)
@@ -161,6 +479,49 @@ impl<'a> Widget for ActionOverlay<'a> {
             self.events.push(e::Nav::ImagePrev.into());
         }
 
+        let mode_idx = NAME_MODES.iter().position(|&m| m == name_options.mode);
+        let mut new_options = name_options;
+        if let Some(new_idx) = widget::DropDownList::new(NAME_MODES, mode_idx)
+            .parent(id)
+            .align_left_of(state.ids.prev)
+            .down_from(state.ids.prev, 0.0)
+            .w_h(64.0, ACTION_HEIGHT)
+            .set(state.ids.name_mode, ui)
+        {
+            if let Some(&mode) = NAME_MODES.get(new_idx) {
+                new_options.mode = mode;
+            }
+        }
+
+        for is_case_sensitive in widget::Toggle::new(name_options.is_case_sensitive)
+            .parent(id)
+            .right_from(state.ids.name_mode, 0.0)
+            .align_top_of(state.ids.name_mode)
+            .w_h(32.0, ACTION_HEIGHT)
+            .label("Aa")
+            .set(state.ids.case_sensitive, ui)
+        {
+            new_options.is_case_sensitive = is_case_sensitive;
+        }
+
+        // Ignores accents and full-width forms when on.
+        for is_folding in widget::Toggle::new(name_options.is_folding)
+            .parent(id)
+            .align_left_of(state.ids.name_mode)
+            .down_from(state.ids.name_mode, 0.0)
+            .w_h(96.0, ACTION_HEIGHT)
+            .label("é = e")
+            .set(state.ids.folding, ui)
+        {
+            new_options.is_folding = is_folding;
+        }
+
+        if new_options != name_options {
+            self.events.push(e::Filter::NameOptions(new_options).into());
+            let error = Query::parse(&state.filter_text, new_options).err();
+            state.update(|s| s.filter_error = error);
+        }
+
         let idx = FILE_SORT_METHODS
             .iter()
             .position(|&x| x == *self.files.current_sort());
@@ -192,30 +553,457 @@ impl<'a> Widget for ActionOverlay<'a> {
             self.events.push(e::SetMeta::Rating(rating).into());
         }
 
-        for enabled in widget::Toggle::new(self.files.is_slideshow_enabled())
+        let slideshow = self.slideshow.settings();
+        let label = if self.slideshow.is_running() {
+            format!("Next in {}s", self.slideshow.remaining().as_secs())
+        } else {
+            "Start Slideshow".to_owned()
+        };
+        for enabled in widget::Toggle::new(self.slideshow.is_running())
             .parent(id)
             .left_from(state.ids.rating, 0.0)
             .align_top_of(state.ids.sort)
             .w_h(192.0, 48.0)
-            .label(if self.files.is_slideshow_enabled() {
-                "Slideshow Running"
-            } else {
-                "Start Slideshow"
-            })
+            .label(&label)
             .set(state.ids.slideshow, ui)
         {
             self.events.push(
                 if enabled {
-                    e::Slideshow::Start(Duration::from_secs(15))
+                    e::Slideshow::Start
                 } else {
                     e::Slideshow::Stop
                 }
                 .into(),
             );
         }
+
+        let interval = slideshow.interval.as_secs() as f64;
+        if let Some(secs) = widget::NumberDialer::new(interval, 1.0, 3600.0, 0)
+            .parent(id)
+            .align_left_of(state.ids.slideshow)
+            .down_from(state.ids.slideshow, 0.0)
+            .w_of(state.ids.slideshow)
+            .h(ACTION_HEIGHT)
+            .label("Seconds per file")
+            .set(state.ids.slideshow_interval, ui)
+        {
+            if secs != interval {
+                let interval = Duration::from_secs(secs as u64);
+                self.events.push(e::Slideshow::Interval(interval).into());
+            }
+        }
+
+        for repeat in widget::Toggle::new(slideshow.repeat)
+            .parent(id)
+            .left_from(state.ids.slideshow, 0.0)
+            .align_top_of(state.ids.slideshow)
+            .w_h(192.0, ACTION_HEIGHT)
+            .label(if slideshow.repeat {
+                "Looping"
+            } else {
+                "Stop at End"
+            })
+            .set(state.ids.slideshow_repeat, ui)
+        {
+            self.events.push(e::Slideshow::Repeat(repeat).into());
+        }
+
+        for shuffle in widget::Toggle::new(slideshow.shuffle)
+            .parent(id)
+            .align_left_of(state.ids.slideshow_repeat)
+            .down_from(state.ids.slideshow_repeat, 0.0)
+            .w_of(state.ids.slideshow_repeat)
+            .h(ACTION_HEIGHT)
+            .label("Shuffle Each Loop")
+            .set(state.ids.slideshow_shuffle, ui)
+        {
+            self.events.push(e::Slideshow::Shuffle(shuffle).into());
+        }
+
+        for pause in widget::Toggle::new(slideshow.pause_on_interaction)
+            .parent(id)
+            .align_left_of(state.ids.slideshow_shuffle)
+            .down_from(state.ids.slideshow_shuffle, 0.0)
+            .w_of(state.ids.slideshow_shuffle)
+            .h(ACTION_HEIGHT)
+            .label("Pause While Interacting")
+            .set(state.ids.slideshow_pause, ui)
+        {
+            self.events
+                .push(e::Slideshow::PauseOnInteraction(pause).into());
+        }
+
+        for wait in widget::Toggle::new(slideshow.wait_for_animation)
+            .parent(id)
+            .align_left_of(state.ids.slideshow_pause)
+            .down_from(state.ids.slideshow_pause, 0.0)
+            .w_of(state.ids.slideshow_pause)
+            .h(ACTION_HEIGHT)
+            .label("Finish Animations")
+            .set(state.ids.slideshow_wait, ui)
+        {
+            self.events
+                .push(e::Slideshow::WaitForAnimation(wait).into());
+        }
+
+        let idx = SLIDESHOW_ORDERS.iter().position(|&x| x == slideshow.order);
+        if let Some(new_idx) = widget::DropDownList::new(SLIDESHOW_ORDERS, idx)
+            .parent(id)
+            .align_left_of(state.ids.slideshow_wait)
+            .down_from(state.ids.slideshow_wait, 0.0)
+            .w_of(state.ids.slideshow_wait)
+            .h(ACTION_HEIGHT)
+            .set(state.ids.slideshow_order, ui)
+        {
+            if Some(new_idx) != idx {
+                if let Some(order) = SLIDESHOW_ORDERS.get(new_idx) {
+                    self.events.push(e::Slideshow::Order(*order).into());
+                }
+            }
+        }
+
+        if slideshow.order == SlideshowOrder::Weighted {
+            let cooldown = slideshow.cooldown as f64;
+            if let Some(value) = widget::NumberDialer::new(cooldown, 0.0, 1000.0, 0)
+                .parent(id)
+                .align_left_of(state.ids.slideshow_order)
+                .down_from(state.ids.slideshow_order, 0.0)
+                .w_of(state.ids.slideshow_order)
+                .h(ACTION_HEIGHT)
+                .label("Skip recent")
+                .set(state.ids.slideshow_cooldown, ui)
+            {
+                if value != cooldown {
+                    self.events
+                        .push(e::Slideshow::Cooldown(value as usize).into());
+                }
+            }
+
+            let count = slideshow.rating_weights.len();
+            if state.ids.rating_weights.len() != count {
+                state.update(|s| {
+                    s.ids
+                        .rating_weights
+                        .resize(count, &mut ui.widget_id_generator())
+                });
+            }
+
+            // Three weights to a row, starting with unrated files.
+            for (i, &weight) in slideshow.rating_weights.iter().enumerate() {
+                let (rating, label) = match i {
+                    0 => (None, "None".to_owned()),
+                    stars => (Some(Rating::from(stars)), format!("{}*", stars)),
+                };
+                let dialer = widget::NumberDialer::new(f64::from(weight), 0.0, 100.0, 0)
+                    .parent(id)
+                    .w_h(64.0, ACTION_HEIGHT)
+                    .label(&label);
+                let dialer = match i {
+                    0 => dialer
+                        .align_left_of(state.ids.slideshow_cooldown)
+                        .down_from(state.ids.slideshow_cooldown, 0.0),
+                    3 => dialer
+                        .align_left_of(state.ids.rating_weights[0])
+                        .down_from(state.ids.rating_weights[0], 0.0),
+                    _ => dialer
+                        .right_from(state.ids.rating_weights[i - 1], 0.0)
+                        .align_top_of(state.ids.rating_weights[i - 1]),
+                };
+
+                if let Some(value) = dialer.set(state.ids.rating_weights[i], ui) {
+                    if value != f64::from(weight) {
+                        self.events
+                            .push(e::Slideshow::RatingWeight(rating, value as u32).into());
+                    }
+                }
+            }
+        }
+
+        let idx = SCALE_MODES.iter().position(|&x| x == self.view.scale_mode);
+        if let Some(new_idx) = widget::DropDownList::new(SCALE_MODES, idx)
+            .parent(id)
+            .align_left_of(state.ids.rating)
+            .down_from(state.ids.rating, 0.0)
+            .w_of(state.ids.rating)
+            .h(ACTION_HEIGHT)
+            .set(state.ids.scale_mode, ui)
+        {
+            if Some(new_idx) != idx {
+                if let Some(mode) = SCALE_MODES.get(new_idx) {
+                    self.events.push(e::View::ScaleMode(*mode).into());
+                }
+            }
+        }
+
+        for upscale in widget::Toggle::new(self.view.upscale)
+            .parent(id)
+            .left_from(state.ids.scale_mode, 0.0)
+            .align_top_of(state.ids.scale_mode)
+            .w_h(192.0, ACTION_HEIGHT)
+            .label(if self.view.upscale {
+                "Upscaling Small Images"
+            } else {
+                "Never Upscale"
+            })
+            .set(state.ids.upscale, ui)
+        {
+            self.events.push(e::View::Upscale(upscale).into());
+        }
+
+        for remember in widget::Toggle::new(self.view.remember_view)
+            .parent(id)
+            .align_left_of(state.ids.scale_mode)
+            .down_from(state.ids.scale_mode, 0.0)
+            .w_of(state.ids.scale_mode)
+            .h(ACTION_HEIGHT)
+            .label(if self.view.remember_view {
+                "Remembering Zoom"
+            } else {
+                "Remember Zoom"
+            })
+            .set(state.ids.remember_view, ui)
+        {
+            self.events.push(e::View::Remember(remember).into());
+        }
+
+        for lock in widget::Toggle::new(self.view.lock_view)
+            .parent(id)
+            .left_from(state.ids.remember_view, 0.0)
+            .align_top_of(state.ids.remember_view)
+            .w_h(192.0, ACTION_HEIGHT)
+            .label(if self.view.lock_view {
+                "View Locked"
+            } else {
+                "Lock View"
+            })
+            .set(state.ids.lock_view, ui)
+        {
+            self.events.push(e::View::Lock(lock).into());
+        }
+
+        for grid in widget::Toggle::new(self.view.pixel_grid)
+            .parent(id)
+            .align_left_of(state.ids.remember_view)
+            .down_from(state.ids.remember_view, 0.0)
+            .w_of(state.ids.remember_view)
+            .h(ACTION_HEIGHT)
+            .label(if self.view.pixel_grid {
+                "Pixel Grid Shown"
+            } else {
+                "Show Pixel Grid"
+            })
+            .set(state.ids.pixel_grid, ui)
+        {
+            self.events.push(e::View::PixelGrid(grid).into());
+        }
+
+        let grid_percent = self.view.pixel_grid_scale * 100.0;
+        if let Some(percent) = widget::NumberDialer::new(grid_percent, 200.0, 10000.0, 0)
+            .parent(id)
+            .left_from(state.ids.pixel_grid, 0.0)
+            .align_top_of(state.ids.pixel_grid)
+            .w_h(192.0, ACTION_HEIGHT)
+            .label("Grid from %")
+            .set(state.ids.pixel_grid_scale, ui)
+        {
+            if percent != grid_percent {
+                self.events
+                    .push(e::View::PixelGridScale(percent / 100.0).into());
+            }
+        }
+
+        let idx = BACKGROUNDS.iter().position(|&x| x == self.view.background);
+        if let Some(new_idx) = widget::DropDownList::new(BACKGROUNDS, idx)
+            .parent(id)
+            .align_left_of(state.ids.pixel_grid)
+            .down_from(state.ids.pixel_grid, 0.0)
+            .w_of(state.ids.pixel_grid)
+            .h(ACTION_HEIGHT)
+            .set(state.ids.background, ui)
+        {
+            if Some(new_idx) != idx {
+                if let Some(background) = BACKGROUNDS.get(new_idx) {
+                    self.events.push(e::View::Background(*background).into());
+                }
+            }
+        }
+
+        if self.view.background == Background::Custom {
+            if state.ids.background_color.len() != 3 {
+                state.update(|s| {
+                    s.ids
+                        .background_color
+                        .resize(3, &mut ui.widget_id_generator())
+                });
+            }
+
+            // Laid out right to left so the sliders line up with the dialer above.
+            const CHANNELS: [(&str, conrod_core::Color); 3] =
+                [("R", color::RED), ("G", color::GREEN), ("B", color::BLUE)];
+            let rgb = self.view.background_color;
+            for (i, (label, channel_color)) in CHANNELS.iter().enumerate().rev() {
+                let right_of = match i {
+                    2 => state.ids.background,
+                    _ => state.ids.background_color[i + 1],
+                };
+                let slider = widget::Slider::new(f32::from(rgb[i]), 0.0, 255.0)
+                    .parent(id)
+                    .left_from(right_of, 0.0)
+                    .align_top_of(right_of)
+                    .w_h(64.0, ACTION_HEIGHT)
+                    .color(*channel_color)
+                    .label(label);
+
+                if let Some(value) = slider.set(state.ids.background_color[i], ui) {
+                    let mut new_rgb = rgb;
+                    new_rgb[i] = value.round() as u8;
+                    if new_rgb != rgb {
+                        self.events.push(e::View::BackgroundColor(new_rgb).into());
+                    }
+                }
+            }
+        }
+
+        let idx = LAYOUTS.iter().position(|&x| x == self.view.layout);
+        if let Some(new_idx) = widget::DropDownList::new(LAYOUTS, idx)
+            .parent(id)
+            .align_left_of(state.ids.background)
+            .down_from(state.ids.background, 0.0)
+            .w_of(state.ids.background)
+            .h(ACTION_HEIGHT)
+            .set(state.ids.layout, ui)
+        {
+            if Some(new_idx) != idx {
+                if let Some(layout) = LAYOUTS.get(new_idx) {
+                    self.events.push(e::View::Layout(*layout).into());
+                }
+            }
+        }
+
+        if self.view.layout == Layout::Spread {
+            for right_to_left in widget::Toggle::new(self.view.right_to_left)
+                .parent(id)
+                .left_from(state.ids.layout, 0.0)
+                .align_top_of(state.ids.layout)
+                .w_h(192.0, ACTION_HEIGHT)
+                .label("Right to Left")
+                .set(state.ids.right_to_left, ui)
+            {
+                self.events.push(e::View::RightToLeft(right_to_left).into());
+            }
+
+            for single in widget::Toggle::new(self.view.single_first_page)
+                .parent(id)
+                .align_left_of(state.ids.layout)
+                .down_from(state.ids.layout, 0.0)
+                .w_of(state.ids.layout)
+                .h(ACTION_HEIGHT)
+                .label("Single First Page")
+                .set(state.ids.single_first_page, ui)
+            {
+                self.events.push(e::View::SingleFirstPage(single).into());
+            }
+        }
+
+        if self.view.layout == Layout::Single {
+            let idx = TRANSITIONS.iter().position(|&x| x == self.view.transition);
+            if let Some(new_idx) = widget::DropDownList::new(TRANSITIONS, idx)
+                .parent(id)
+                .align_left_of(state.ids.layout)
+                .down_from(state.ids.layout, 0.0)
+                .w_of(state.ids.layout)
+                .h(ACTION_HEIGHT)
+                .set(state.ids.transition, ui)
+            {
+                if Some(new_idx) != idx {
+                    if let Some(transition) = TRANSITIONS.get(new_idx) {
+                        self.events.push(e::View::Transition(*transition).into());
+                    }
+                }
+            }
+
+            if self.view.transition != Transition::None {
+                let millis = self.view.transition_duration.as_millis() as f64;
+                if let Some(value) = widget::NumberDialer::new(millis, 50.0, 5000.0, 0)
+                    .parent(id)
+                    .left_from(state.ids.transition, 0.0)
+                    .align_top_of(state.ids.transition)
+                    .w_h(192.0, ACTION_HEIGHT)
+                    .label("Transition ms")
+                    .set(state.ids.transition_duration, ui)
+                {
+                    if value != millis {
+                        let duration = Duration::from_millis(value as u64);
+                        self.events
+                            .push(e::View::TransitionDuration(duration).into());
+                    }
+                }
+            }
+        }
     }
 }
 
+const RATING_MODE_WIDTH: f64 = 90.0;
+const FILTER_COUNT_WIDTH: f64 = 90.0;
+const SAVED_FILTER_BUTTON_WIDTH: f64 = 48.0;
+/// How long after the last edit to the filter text it is applied.
+const FILTER_DELAY: Duration = Duration::from_millis(250);
+
+static RATING_FILTER_MODES: &[&str] = &[
+    "Any Rating",
+    "At Least",
+    "Exactly",
+    "Between",
+    "Unrated",
+    "Rated",
+];
+
+/// Whether a text box has keyboard focus, which its text edit captures rather than the text box.
+fn is_capturing_keyboard(ui: &conrod_core::UiCell, text_box: widget::Id) -> bool {
+    ui.global_input()
+        .current
+        .widget_capturing_keyboard
+        .is_some_and(|w| w == text_box || ui.widget_graph().depth_parent(w) == Some(text_box))
+}
+
+/// The index in `RATING_FILTER_MODES` of the filter.
+fn rating_filter_mode(filter: &Option<RatingFilter>) -> usize {
+    match filter {
+        None => 0,
+        Some(RatingFilter::AtLeast(_)) => 1,
+        Some(RatingFilter::Exactly(_)) => 2,
+        Some(RatingFilter::Between(_, _)) => 3,
+        Some(RatingFilter::Unrated) => 4,
+        Some(RatingFilter::Rated) => 5,
+    }
+}
+
+/// Switches the filter to another of `RATING_FILTER_MODES`, keeping its stars where possible.
+fn with_rating_filter_mode(filter: &Option<RatingFilter>, mode: usize) -> Option<RatingFilter> {
+    let (min, max) = filter
+        .as_ref()
+        .and_then(RatingFilter::bounds)
+        .unwrap_or((Rating::min_value(), Rating::max_value()));
+    match mode {
+        1 => Some(RatingFilter::AtLeast(Rating::from(min))),
+        2 => Some(RatingFilter::Exactly(Rating::from(min))),
+        3 => Some(RatingFilter::Between(Rating::from(min), Rating::from(max))),
+        4 => Some(RatingFilter::Unrated),
+        5 => Some(RatingFilter::Rated),
+        _ => None,
+    }
+}
+
+/// Moves the nearest end of a range of ratings to the clicked star.
+fn extend_range(min: usize, max: usize, clicked: usize) -> RatingFilter {
+    let (min, max) = if clicked <= min || (clicked < max && clicked - min <= max - clicked) {
+        (clicked, max)
+    } else {
+        (min, clicked)
+    };
+    RatingFilter::Between(Rating::from(min), Rating::from(max))
+}
+
 fn build_selected_style() -> list_item::Style {
     let mut style = list_item::Style::default();
     style.label_color = Some(color::DARK_RED);
@@ -32,17 +32,27 @@ pub struct StarRating<'a> {
     #[conrod(common_builder)]
     common: widget::CommonBuilder,
     rating: Option<Rating>,
+    /// The first and last stars to fill, if any.
+    filled: Option<(usize, usize)>,
     res: &'a Resources,
 }
 
 impl<'a> StarRating<'a> {
     pub fn new(rating: Option<Rating>, res: &'a Resources) -> Self {
+        let filled = rating.clone().map(|r| (1, r.into()));
         StarRating {
             common: widget::CommonBuilder::default(),
             rating,
+            filled,
             res,
         }
     }
+
+    /// Fills the stars from `min` to `max` instead of up to the rating.
+    pub fn filled(mut self, filled: Option<(usize, usize)>) -> Self {
+        self.filled = filled;
+        self
+    }
 }
 
 impl<'a> Widget for StarRating<'a> {
@@ -80,7 +90,9 @@ impl<'a> Widget for StarRating<'a> {
         let mut new_rating = None;
 
         for i in 0..5 {
-            let is_filled = i < rating;
+            let is_filled = self
+                .filled
+                .is_some_and(|(min, max)| (min..=max).contains(&(i + 1)));
             let image = if is_filled {
                 self.res.images.star_filled
             } else {
@@ -0,0 +1,360 @@
+use conrod_core::{widget, Positionable, Sizeable, Widget};
+use std::path::PathBuf;
+
+use super::viewer::background_color;
+use crate::data::{File, FileList, ViewSettings};
+use crate::systems::{events as e, AppEvent, EventSystem, Pages, ThumbnailState};
+
+/// How far a single point of mouse wheel scrolling moves the strip.
+const SCROLL_SPEED: f64 = 6.0;
+/// The fraction of the remaining scroll distance covered each frame.
+const SCROLL_EASING: f64 = 0.3;
+/// How much of the view a single pan step moves by.
+const PAN_FRACTION: f64 = 0.125;
+/// Pages either side of the visible ones which are loaded ahead of time.
+const PRELOAD_PAGES: usize = 1;
+
+widget_ids!(struct Ids {
+    background,
+    pages[],
+});
+
+/// A point in the strip, relative to the top of a page.
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Position {
+    index: usize,
+    offset: f64,
+}
+
+pub struct State {
+    ids: Ids,
+    /// The page at the top of the view.
+    position: Position,
+    /// Scrolling which hasn't been applied yet, eased in over the next frames.
+    remaining: f64,
+    /// The current index last seen in the file list.
+    synced_index: usize,
+    /// Indexes sent to the file list which it hasn't caught up with yet.
+    requested: Vec<usize>,
+    retained: Vec<PathBuf>,
+}
+
+/// Shows files stacked vertically and fitted to the width, scrolling smoothly between them.
+#[derive(WidgetCommon)]
+pub struct ScrollViewer<'a> {
+    #[conrod(common_builder)]
+    common: widget::CommonBuilder,
+    files: &'a FileList,
+    pages: &'a Pages,
+    settings: &'a ViewSettings,
+    events: &'a mut EventSystem,
+}
+
+impl<'a> ScrollViewer<'a> {
+    pub fn new(
+        files: &'a FileList,
+        pages: &'a Pages,
+        settings: &'a ViewSettings,
+        events: &'a mut EventSystem,
+    ) -> Self {
+        ScrollViewer {
+            common: widget::CommonBuilder::default(),
+            files,
+            pages,
+            settings,
+            events,
+        }
+    }
+}
+
+impl<'a> Widget for ScrollViewer<'a> {
+    type State = State;
+    type Style = ();
+    type Event = ();
+
+    fn init_state(&self, id_gen: widget::id::Generator) -> Self::State {
+        let index = self.files.current_index();
+        State {
+            ids: Ids::new(id_gen),
+            position: Position { index, offset: 0.0 },
+            remaining: 0.0,
+            synced_index: index,
+            requested: Vec::new(),
+            retained: Vec::new(),
+        }
+    }
+
+    fn style(&self) -> Self::Style {}
+
+    fn update(self, args: widget::UpdateArgs<Self>) -> Self::Event {
+        let widget::UpdateArgs {
+            state,
+            ui,
+            id,
+            rect,
+            ..
+        } = args;
+        let [uw, uh] = rect.dim();
+
+        if let Some(background) = background_color(self.settings) {
+            widget::Rectangle::fill_with([uw, uh], background)
+                .parent(id)
+                .graphics_for(id)
+                .xy(rect.xy())
+                .set(state.ids.background, ui);
+        }
+
+        let count = self.files.len();
+        if count == 0 {
+            return;
+        }
+
+        let height = |i: usize| page_height(self.files, self.pages, i, uw);
+
+        let mut position = state.position;
+        let mut remaining = state.remaining;
+        let mut requested = state.requested.clone();
+
+        // Jump to the top of files chosen elsewhere, such as the filmstrip.
+        let current = self.files.current_index();
+        if current != state.synced_index {
+            if !requested.contains(&current) {
+                position = Position {
+                    index: current,
+                    offset: 0.0,
+                };
+                remaining = 0.0;
+            }
+            requested.retain(|&i| i != current);
+        }
+
+        let mut new_events = Vec::new();
+        for event in self.events.events() {
+            if let AppEvent::View(e::View::Pan(pan)) = event {
+                match pan {
+                    e::Pan::Up => remaining -= uh * PAN_FRACTION,
+                    e::Pan::Down => remaining += uh * PAN_FRACTION,
+                    e::Pan::PageUp => remaining -= uh,
+                    e::Pan::PageDown => remaining += uh,
+                    e::Pan::Left => new_events.push(e::Nav::ImagePrev.into()),
+                    e::Pan::Right => new_events.push(e::Nav::ImageNext.into()),
+                }
+            }
+        }
+
+        let input = ui.widget_input(id);
+        for scroll in input.scrolls() {
+            remaining += scroll.y * SCROLL_SPEED;
+        }
+        for drag in input.drags().left() {
+            position.offset += drag.delta_xy[1];
+        }
+
+        let step = if remaining.abs() < 0.5 {
+            remaining
+        } else {
+            remaining * SCROLL_EASING
+        };
+        position.offset += step;
+        remaining -= step;
+
+        let (normalized, is_clamped) = normalize(position, count, uh, &height);
+        position = normalized;
+        if is_clamped {
+            remaining = 0.0;
+        }
+
+        if position.index != state.position.index {
+            requested.push(position.index);
+            new_events.push(e::Nav::ImageIndex(position.index).into());
+        }
+
+        let is_changed = position != state.position
+            || remaining != state.remaining
+            || requested != state.requested
+            || current != state.synced_index;
+        if is_changed {
+            state.update(|s| {
+                s.position = position;
+                s.remaining = remaining;
+                s.requested = requested;
+                s.synced_index = current;
+            });
+        }
+
+        let mut visible = Vec::new();
+        let mut top = -position.offset;
+        let mut index = position.index;
+        while top < uh && index < count {
+            let h = page_height(self.files, self.pages, index, uw);
+            visible.push((index, top, h));
+            top += h;
+            index += 1;
+        }
+
+        if state.ids.pages.len() < visible.len() {
+            state.update(|s| {
+                s.ids
+                    .pages
+                    .resize(visible.len(), &mut ui.widget_id_generator())
+            });
+        }
+
+        for (&page_id, &(index, top, h)) in state.ids.pages.iter().zip(visible.iter()) {
+            let page = self.files.get_file(index).map(|f| self.pages.get(&f.path));
+            match page {
+                Some(Some(ThumbnailState::Loaded(page))) => widget::Image::new(page.id)
+                    .parent(id)
+                    .graphics_for(id)
+                    .w_h(uw, h)
+                    .top_left_with_margins_on(id, top, 0.0)
+                    .set(page_id, ui),
+                _ => widget::Rectangle::fill_with([uw, h], ui.theme.shape_color.alpha(0.5))
+                    .parent(id)
+                    .graphics_for(id)
+                    .top_left_with_margins_on(id, top, 0.0)
+                    .set(page_id, ui),
+            }
+        }
+
+        let first = position.index.saturating_sub(PRELOAD_PAGES);
+        let last = (index + PRELOAD_PAGES).min(count);
+        let retained: Vec<PathBuf> = (first..last)
+            .filter_map(|i| self.files.get_file(i))
+            .map(|f| f.path.clone())
+            .collect();
+        for path in &retained {
+            if self.pages.get(path).is_none() {
+                new_events.push(AppEvent::LoadPage(path.clone()));
+            }
+        }
+        if retained != state.retained {
+            new_events.push(AppEvent::RetainPages(retained.clone()));
+            state.update(|s| s.retained = retained);
+        }
+
+        self.events.push_all(new_events);
+    }
+}
+
+/// The height of the page at `index` when fitted to `width`.
+fn page_height(files: &FileList, pages: &Pages, index: usize, width: f64) -> f64 {
+    files
+        .get_file(index)
+        .map(|file| {
+            let [w, h] = page_size(pages, file);
+            width * h / w
+        })
+        .unwrap_or(width)
+}
+
+/// The size of the decoded page, or from its image info, or square until either is known.
+fn page_size(pages: &Pages, file: &File) -> [f64; 2] {
+    let size = match (pages.get(&file.path), &file.info) {
+        (Some(ThumbnailState::Loaded(page)), _) => [page.w, page.h],
+        (_, Some(Ok(info))) => [info.width, info.height],
+        _ => [1, 1],
+    };
+    match size {
+        [w, h] if w > 0 && h > 0 => [w as f64, h as f64],
+        _ => [1.0, 1.0],
+    }
+}
+
+/// Moves the position onto the page it lies within and keeps the strip filling the view.
+///
+/// Also returns whether the position was stopped at either end of the strip.
+fn normalize(
+    mut position: Position,
+    count: usize,
+    view_height: f64,
+    height: &dyn Fn(usize) -> f64,
+) -> (Position, bool) {
+    loop {
+        if position.offset < 0.0 && position.index > 0 {
+            position.index -= 1;
+            position.offset += height(position.index);
+        } else if position.offset >= height(position.index) && position.index + 1 < count {
+            position.offset -= height(position.index);
+            position.index += 1;
+        } else {
+            break;
+        }
+    }
+
+    if position.offset < 0.0 {
+        position.offset = 0.0;
+        return (position, true);
+    }
+
+    // Stop once the bottom of the last page reaches the bottom of the view.
+    let mut below = 0.0;
+    let mut index = position.index;
+    while index < count && below < position.offset + view_height {
+        below += height(index);
+        index += 1;
+    }
+    let max_offset = below - view_height;
+    if index == count && position.offset > max_offset {
+        position.offset = max_offset;
+        while position.offset < 0.0 && position.index > 0 {
+            position.index -= 1;
+            position.offset += height(position.index);
+        }
+        position.offset = position.offset.max(0.0);
+        return (position, true);
+    }
+
+    (position, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(index: usize, offset: f64) -> Position {
+        Position { index, offset }
+    }
+
+    #[test]
+    pub fn normalize_moves_between_pages() {
+        let height = |i: usize| 100.0 + i as f64 * 100.0;
+
+        assert_eq!(
+            normalize(at(1, 50.0), 5, 100.0, &height),
+            (at(1, 50.0), false)
+        );
+        assert_eq!(
+            normalize(at(0, 250.0), 5, 100.0, &height),
+            (at(1, 150.0), false)
+        );
+        assert_eq!(
+            normalize(at(2, -50.0), 5, 100.0, &height),
+            (at(1, 150.0), false)
+        );
+        assert_eq!(
+            normalize(at(0, -10.0), 5, 100.0, &height),
+            (at(0, 0.0), true)
+        );
+    }
+
+    #[test]
+    pub fn normalize_stops_at_end_of_strip() {
+        let height = |_| 100.0;
+
+        // The last page's bottom stays at the bottom of the view.
+        assert_eq!(
+            normalize(at(4, 50.0), 5, 100.0, &height),
+            (at(4, 0.0), true)
+        );
+        assert_eq!(
+            normalize(at(4, 0.0), 5, 150.0, &height),
+            (at(3, 50.0), true)
+        );
+        // A strip shorter than the view can't scroll at all.
+        assert_eq!(
+            normalize(at(1, 20.0), 2, 500.0, &height),
+            (at(0, 0.0), true)
+        );
+    }
+}
@@ -1,13 +1,30 @@
-use conrod_core::{widget, Positionable, Sizeable, Widget};
+use conrod_core::{color, widget, Colorable, Positionable, Sizeable, Widget};
+use std::path::PathBuf;
 
+use crate::data::{Background, Layout, SavedView, ScaleMode, Transition, ViewSettings};
+use crate::res::{Resources, CHECKERBOARD_SIZE};
 use crate::systems::{events as e, EventSystem};
 
 widget_ids!(struct Ids {
+    background,
+    checkerboard[],
+    outgoing,
     image,
+    companion,
+    grid_columns,
+    grid_rows,
+    hovered_pixel,
+    pixel_position,
+    minimap_background,
+    minimap_image,
+    minimap_companion,
+    minimap_view,
+    transition_fade,
+    zoom,
 });
 
 pub enum ImageScale {
-    FitAll,
+    Mode(ScaleMode),
     Scale {
         scale: f64,
         offset_top: f64,
@@ -15,30 +32,78 @@ pub enum ImageScale {
     },
 }
 
+#[derive(Copy, Clone)]
 pub struct ImageData {
     id: conrod_core::image::Id,
     w: u32,
     h: u32,
 }
 
+impl ImageData {
+    fn wh(&self) -> [f64; 2] {
+        [self.w as f64, self.h as f64]
+    }
+}
+
+/// The previous image and where it was, shown while the transition away from it runs.
+#[derive(Copy, Clone)]
+struct OutgoingImage {
+    id: conrod_core::image::Id,
+    top: f64,
+    left: f64,
+    w: f64,
+    h: f64,
+    progress: f64,
+}
+
 pub struct State {
     ids: Ids,
     image: Option<ImageData>,
+    /// The image beside `image` in a two page spread.
+    companion: Option<ImageData>,
+    file: Option<PathBuf>,
     scale: ImageScale,
+    outgoing: Option<OutgoingImage>,
+}
+
+impl State {
+    /// The size of the image together with its companion.
+    fn page_size(&self) -> Option<[f64; 2]> {
+        self.image
+            .map(|image| spread_size(image.wh(), self.companion.map(|c| c.wh())))
+    }
+
+    /// The view to remember for the current image, if it has been zoomed or panned.
+    fn saved_view(&self, upscale: bool, uw: f64, uh: f64) -> Option<SavedView> {
+        match (self.page_size(), &self.scale) {
+            (Some(page), ImageScale::Scale { .. }) => {
+                Some(ScaledImage::new(page, &self.scale, upscale, uw, uh).saved_view(uw, uh))
+            }
+            _ => None,
+        }
+    }
 }
 
 #[derive(WidgetCommon)]
 pub struct ImageViewer<'a> {
     #[conrod(common_builder)]
     common: widget::CommonBuilder,
-    events: &'a EventSystem,
+    events: &'a mut EventSystem,
+    settings: &'a ViewSettings,
+    res: &'a Resources,
 }
 
 impl<'a> ImageViewer<'a> {
-    pub fn new(events: &'a EventSystem) -> Self {
+    pub fn new(
+        events: &'a mut EventSystem,
+        settings: &'a ViewSettings,
+        res: &'a Resources,
+    ) -> Self {
         ImageViewer {
             common: widget::CommonBuilder::default(),
             events,
+            settings,
+            res,
         }
     }
 }
@@ -52,15 +117,28 @@ impl<'a> Widget for ImageViewer<'a> {
         State {
             ids: Ids::new(id_gen),
             image: None,
-            scale: ImageScale::FitAll,
+            companion: None,
+            file: None,
+            scale: ImageScale::Mode(ScaleMode::FitAll),
+            outgoing: None,
         }
     }
 
     fn style(&self) -> Self::Style {}
 
     fn update(self, args: widget::UpdateArgs<Self>) -> Self::Event {
-        let widget::UpdateArgs { state, ui, id, .. } = args;
+        let widget::UpdateArgs {
+            state,
+            ui,
+            id,
+            rect,
+            ..
+        } = args;
+        let [uw, uh] = ui.wh_of(id).unwrap_or(ui.window_dim());
 
+        let is_right_to_left =
+            self.settings.layout == Layout::Spread && self.settings.right_to_left;
+        let mut new_events: Vec<e::AppEvent> = Vec::new();
         for event in self.events.events() {
             match event {
                 e::AppEvent::Image(event) => match event {
@@ -72,95 +150,526 @@ impl<'a> Widget for ImageViewer<'a> {
                             }
                         });
                     }
-                    e::Image::Loaded { id, w, h, .. } => {
+                    e::Image::Loaded { id, w, h, file, .. } => {
                         log::info!("Loading new image: {:?}, {}x{}", id, w, h);
+                        let image = ImageData {
+                            id: *id,
+                            w: *w,
+                            h: *h,
+                        };
+
+                        let previous = state.saved_view(self.settings.upscale, uw, uh);
+                        if self.settings.remember_view {
+                            if let Some(path) = state.file.as_ref().filter(|p| **p != file.path) {
+                                new_events.push(e::SetMeta::View(path.clone(), previous).into());
+                            }
+                        }
+
+                        let has_transition = self.settings.transition != Transition::None
+                            && self.settings.layout == Layout::Single;
+                        let outgoing = match state.image {
+                            Some(prev) if has_transition && state.companion.is_none() => {
+                                let scaled = ScaledImage::new(
+                                    prev.wh(),
+                                    &state.scale,
+                                    self.settings.upscale,
+                                    uw,
+                                    uh,
+                                );
+                                Some(OutgoingImage {
+                                    id: prev.id,
+                                    top: scaled.top,
+                                    left: scaled.left,
+                                    w: scaled.w,
+                                    h: scaled.h,
+                                    progress: 0.0,
+                                })
+                            }
+                            _ => None,
+                        };
+
+                        let scale = match (&file.view, previous) {
+                            (Some(view), _) if self.settings.remember_view => {
+                                restore_view(view, image.wh(), uw, uh)
+                            }
+                            (_, Some(view)) if self.settings.lock_view => {
+                                restore_view(&view, image.wh(), uw, uh)
+                            }
+                            _ => ImageScale::Mode(self.settings.scale_mode),
+                        };
                         state.update(|s| {
-                            s.image = Some(ImageData {
-                                id: *id,
-                                w: *w,
-                                h: *h,
-                            });
-                            s.scale = ImageScale::FitAll;
+                            s.image = Some(image);
+                            s.companion = None;
+                            s.file = Some(file.path.clone());
+                            s.scale = scale;
+                            s.outgoing = outgoing;
                         });
                     }
+                    e::Image::CompanionLoaded { id, w, h } => {
+                        let companion = ImageData {
+                            id: *id,
+                            w: *w,
+                            h: *h,
+                        };
+                        state.update(|s| s.companion = Some(companion));
+                    }
+                    e::Image::CompanionUnloaded => state.update(|s| s.companion = None),
+                    e::Image::Transition { outgoing, progress } => {
+                        if state.outgoing.is_some() {
+                            state.update(|s| {
+                                if let Some(o) = s.outgoing.as_mut() {
+                                    o.id = *outgoing;
+                                    o.progress = *progress;
+                                }
+                            });
+                        }
+                    }
+                    e::Image::TransitionFinished => state.update(|s| s.outgoing = None),
+                    e::Image::LoopCompleted => (),
                 },
+                e::AppEvent::Window(e::Window::Close) if self.settings.remember_view => {
+                    if let Some(path) = &state.file {
+                        let view = state.saved_view(self.settings.upscale, uw, uh);
+                        new_events.push(e::SetMeta::View(path.clone(), view).into());
+                    }
+                }
+                e::AppEvent::View(e::View::ScaleMode(mode)) => {
+                    state.update(|s| s.scale = ImageScale::Mode(*mode));
+                }
+                e::AppEvent::View(view) => {
+                    if let Some(page) = state.page_size() {
+                        let current =
+                            ScaledImage::new(page, &state.scale, self.settings.upscale, uw, uh);
+                        let center = [uw / 2.0, uh / 2.0];
+                        let scale = match view {
+                            e::View::ZoomIn => Some(
+                                current.zoom_at(step_scale(current.scale, ZOOM_STEP, 1.0), center),
+                            ),
+                            e::View::ZoomOut => Some(
+                                current.zoom_at(step_scale(current.scale, ZOOM_STEP, -1.0), center),
+                            ),
+                            e::View::ZoomReset => Some(ImageScale::Mode(self.settings.scale_mode)),
+                            e::View::Pan(pan) => {
+                                let panned = current.pan(*pan, uw, uh);
+                                if panned.is_none() {
+                                    new_events.extend(
+                                        pan_fallback(*pan, is_right_to_left).map(Into::into),
+                                    );
+                                }
+                                panned
+                            }
+                            _ => None,
+                        };
+                        if let Some(scale) = scale {
+                            state.update(|s| s.scale = scale);
+                        }
+                    } else if let e::View::Pan(pan) = view {
+                        // Nothing to pan while the image is loading or failed to, so navigate.
+                        new_events.extend(pan_fallback(*pan, is_right_to_left).map(Into::into));
+                    }
+                }
                 _ => (),
             }
         }
+        self.events.push_all(new_events);
+
+        let background = background_color(self.settings);
+        if let Some(background) = background {
+            widget::Rectangle::fill_with([uw, uh], background)
+                .parent(id)
+                .graphics_for(id)
+                .xy(rect.xy())
+                .set(state.ids.background, ui);
+        }
+
+        if let (Some(image), Some(page)) = (state.image, state.page_size()) {
+            let scaled = ScaledImage::new(page, &state.scale, self.settings.upscale, uw, uh);
+            let pages = spread_pages(
+                &image,
+                state.companion.as_ref(),
+                self.settings.right_to_left,
+                scaled.w,
+            );
+
+            let transition = match &state.outgoing {
+                Some(outgoing) => {
+                    TransitionFrame::new(self.settings.transition, outgoing.progress, uw)
+                }
+                None => TransitionFrame::new(Transition::None, 1.0, uw),
+            };
+
+            // The checkerboard stays put, so it would show through a sliding image.
+            let is_sliding =
+                state.outgoing.is_some() && self.settings.transition == Transition::Slide;
+            if background.is_none() && !is_sliding {
+                let tiles = scaled.checkerboard_tiles(uw, uh);
+                if state.ids.checkerboard.len() < tiles.len() {
+                    state.update(|s| {
+                        s.ids
+                            .checkerboard
+                            .resize(tiles.len(), &mut ui.widget_id_generator())
+                    });
+                }
+                for (&tile_id, [top, left, w, h]) in state.ids.checkerboard.iter().zip(tiles) {
+                    let source = conrod_core::Rect::from_corners([0.0, 0.0], [w, h]);
+                    widget::Image::new(self.res.images.checkerboard)
+                        .source_rectangle(source)
+                        .parent(id)
+                        .graphics_for(id)
+                        .w_h(w, h)
+                        .top_left_with_margins_on(id, top, left)
+                        .set(tile_id, ui);
+                }
+            }
+
+            if let Some(dx) = transition.incoming {
+                for (&page_id, &(image_id, x, w)) in [state.ids.image, state.ids.companion]
+                    .iter()
+                    .zip(pages.iter())
+                {
+                    widget::Image::new(image_id)
+                        .parent(id)
+                        .graphics_for(id)
+                        .w_h(w, scaled.h)
+                        .top_left_with_margins(scaled.top, scaled.left + x + dx)
+                        .set(page_id, ui);
+                }
+            }
+
+            if let (Some(outgoing), Some(dx)) = (state.outgoing, transition.outgoing) {
+                widget::Image::new(outgoing.id)
+                    .parent(id)
+                    .graphics_for(id)
+                    .w_h(outgoing.w, outgoing.h)
+                    .top_left_with_margins(outgoing.top, outgoing.left + dx)
+                    .set(state.ids.outgoing, ui);
+            }
+
+            if transition.black > 0.0 {
+                widget::Rectangle::fill_with([uw, uh], color::BLACK.alpha(transition.black as f32))
+                    .parent(id)
+                    .graphics_for(id)
+                    .xy(rect.xy())
+                    .set(state.ids.transition_fade, ui);
+            }
+
+            // Pixels don't line up between the pages of a spread so there's no grid.
+            let has_grid = self.settings.pixel_grid && state.companion.is_none();
+            if has_grid && scaled.scale >= self.settings.pixel_grid_scale {
+                // Convert from the view's top left origin to conrod's absolute coordinates.
+                let to_abs = |[x, y]: [f64; 2]| [rect.left() + x, rect.top() - y];
+                let style = widget::line::Style::solid()
+                    .color(conrod_core::color::BLACK.alpha(0.5))
+                    .thickness(1.0);
+                let [columns, rows] = scaled.grid(uw, uh);
+                widget::PointPath::abs_styled(columns.into_iter().map(to_abs), style)
+                    .parent(id)
+                    .graphics_for(id)
+                    .set(state.ids.grid_columns, ui);
+                widget::PointPath::abs_styled(rows.into_iter().map(to_abs), style)
+                    .parent(id)
+                    .graphics_for(id)
+                    .set(state.ids.grid_rows, ui);
+
+                let hovered = ui
+                    .widget_input(id)
+                    .mouse()
+                    .map(|m| {
+                        let [x, y] = m.rel_xy();
+                        [x + uw / 2.0, uh / 2.0 - y]
+                    })
+                    .and_then(|cursor| scaled.pixel_at(cursor));
+                if let Some([px, py]) = hovered {
+                    widget::Rectangle::outline_styled(
+                        [scaled.scale, scaled.scale],
+                        style.color(conrod_core::color::WHITE).thickness(2.0),
+                    )
+                    .parent(id)
+                    .graphics_for(id)
+                    .top_left_with_margins_on(
+                        id,
+                        scaled.top + py * scaled.scale,
+                        scaled.left + px * scaled.scale,
+                    )
+                    .set(state.ids.hovered_pixel, ui);
+
+                    widget::Text::new(&format!("{}, {}", px, py))
+                        .parent(id)
+                        .graphics_for(id)
+                        .font_size(ui.theme.font_size_small)
+                        .color(ui.theme.label_color)
+                        .bottom_left_with_margin(8.0)
+                        .set(state.ids.pixel_position, ui);
+                }
+            }
+
+            let minimap = Minimap::new(page, &scaled, uw, uh);
+            if let Some(minimap) = &minimap {
+                widget::Rectangle::fill_with(
+                    [
+                        minimap.w + MINIMAP_BORDER * 2.0,
+                        minimap.h + MINIMAP_BORDER * 2.0,
+                    ],
+                    color::BLACK.alpha(0.6),
+                )
+                .parent(id)
+                .graphics_for(id)
+                .top_left_with_margins_on(
+                    id,
+                    minimap.top - MINIMAP_BORDER,
+                    minimap.left - MINIMAP_BORDER,
+                )
+                .set(state.ids.minimap_background, ui);
+
+                let minimap_ids = [state.ids.minimap_image, state.ids.minimap_companion];
+                let ratio = minimap.w / scaled.w;
+                for (&page_id, &(image_id, x, w)) in minimap_ids.iter().zip(pages.iter()) {
+                    widget::Image::new(image_id)
+                        .parent(id)
+                        .graphics_for(id)
+                        .w_h(w * ratio, minimap.h)
+                        .top_left_with_margins_on(id, minimap.top, minimap.left + x * ratio)
+                        .set(page_id, ui);
+                }
 
-        if let Some(image) = &state.image {
-            let [uw, uh] = ui.wh_of(id).unwrap_or(ui.window_dim());
-            let scaled = ScaledImage::new(&image, &state.scale, uw, uh);
+                let [top, left, w, h] = minimap.view_rect(&scaled, uw, uh);
+                widget::Rectangle::outline_styled(
+                    [w, h],
+                    widget::line::Style::solid()
+                        .color(color::WHITE)
+                        .thickness(MINIMAP_BORDER),
+                )
+                .parent(id)
+                .graphics_for(id)
+                .top_left_with_margins_on(id, top, left)
+                .set(state.ids.minimap_view, ui);
+            }
 
-            widget::Image::new(image.id)
+            widget::Text::new(&format!("{:.0}%", scaled.scale * 100.0))
                 .parent(id)
                 .graphics_for(id)
-                .w_h(scaled.w, scaled.h)
-                .top_left_with_margins(scaled.top, scaled.left)
-                .set(state.ids.image, ui);
+                .font_size(ui.theme.font_size_small)
+                .color(ui.theme.label_color)
+                .bottom_right_with_margin(8.0)
+                .set(state.ids.zoom, ui);
 
+            // Converts from the view's centered coordinates to the top left origin.
+            let to_view = |[x, y]: [f64; 2]| [x + uw / 2.0, uh / 2.0 - y];
             let input = ui.widget_input(id);
+
+            if let Some(minimap) = &minimap {
+                // Pressing in the minimap jumps to that point and dragging from there pans.
+                for (xy, _) in input.presses().mouse().left() {
+                    if minimap.contains(to_view(xy)) {
+                        let scale = minimap.center_on(&scaled, to_view(xy), uw, uh);
+                        state.update(|s| s.scale = scale);
+                    }
+                }
+            }
+
             for drag in input.drags() {
                 use conrod_core::input::MouseButton;
-                if let Some(scale) = match (drag.button, &state.scale) {
-                    (
-                        MouseButton::Left,
-                        ImageScale::Scale {
-                            scale,
-                            offset_top,
-                            offset_left,
-                        },
-                    ) => Some(ImageScale::Scale {
-                        scale: *scale,
-                        offset_top: offset_top - drag.delta_xy[1],
-                        offset_left: offset_left + drag.delta_xy[0],
-                    }),
-                    (MouseButton::Left, _) => Some(ImageScale::Scale {
-                        scale: scaled.scale,
-                        offset_top: scaled.top,
-                        offset_left: scaled.left,
-                    }),
-                    _ => None,
-                } {
+                let current = ScaledImage::new(page, &state.scale, self.settings.upscale, uw, uh);
+                let scale = match &minimap {
+                    Some(minimap) if minimap.contains(to_view(drag.origin)) => match drag.button {
+                        MouseButton::Left => Some(minimap.drag(&current, drag.delta_xy)),
+                        _ => None,
+                    },
+                    _ => match drag.button {
+                        MouseButton::Left => Some(ImageScale::Scale {
+                            scale: current.scale,
+                            offset_top: current.top - drag.delta_xy[1],
+                            offset_left: current.left + drag.delta_xy[0],
+                        }),
+                        _ => None,
+                    },
+                };
+                if let Some(scale) = scale {
                     state.update(|s| s.scale = scale);
                 }
             }
 
+            let cursor = input
+                .mouse()
+                .map(|m| to_view(m.rel_xy()))
+                .unwrap_or([uw / 2.0, uh / 2.0]);
             for scroll in input.scrolls() {
-                let scale = match state.scale {
-                    ImageScale::FitAll => ImageScale::Scale {
-                        scale: scaled.scale,
-                        offset_top: scaled.top,
-                        offset_left: scaled.left,
-                    },
-                    ImageScale::Scale {
-                        scale,
-                        offset_top,
-                        offset_left,
-                    } => ImageScale::Scale {
-                        scale: adjust_scale(scale, &scroll),
-                        offset_top,
-                        offset_left,
-                    },
-                };
+                let current = ScaledImage::new(page, &state.scale, self.settings.upscale, uw, uh);
+                let scale = current.zoom_at(adjust_scale(current.scale, &scroll), cursor);
                 state.update(|s| s.scale = scale);
             }
         }
     }
 }
 
+/// Zoom levels which scrolling stops at when passing over them.
+const SNAP_SCALES: &[f64] = &[0.25, 0.5, 1.0, 2.0, 4.0];
+/// The scale factor of a single zoom step.
+const ZOOM_STEP: f64 = 1.2;
+/// How much of the view a single pan step moves by.
+const PAN_FRACTION: f64 = 0.125;
+/// The longest edge of the minimap.
+const MINIMAP_SIZE: f64 = 160.0;
+const MINIMAP_MARGIN: f64 = 8.0;
+const MINIMAP_BORDER: f64 = 2.0;
+
+fn restore_view(
+    view: &SavedView,
+    [w, h]: [f64; 2],
+    full_width: f64,
+    full_height: f64,
+) -> ImageScale {
+    ImageScale::Scale {
+        scale: view.scale,
+        offset_top: full_height / 2.0 - view.center_y * h * view.scale,
+        offset_left: full_width / 2.0 - view.center_x * w * view.scale,
+    }
+}
+
+/// The color filling the view behind the image, or `None` for the checkerboard.
+pub(super) fn background_color(settings: &ViewSettings) -> Option<color::Color> {
+    match settings.background {
+        Background::Checkerboard => None,
+        Background::Black => Some(color::BLACK),
+        Background::White => Some(color::WHITE),
+        Background::Custom => {
+            let [r, g, b] = settings.background_color;
+            Some(color::rgb_bytes(r, g, b))
+        }
+    }
+}
+
+/// The size of a spread with the companion scaled to the same height as the image.
+fn spread_size([w, h]: [f64; 2], companion: Option<[f64; 2]>) -> [f64; 2] {
+    match companion {
+        Some([cw, ch]) => [w + cw * h / ch, h],
+        None => [w, h],
+    }
+}
+
+/// Splits a spread `width` wide into `(image, x, width)` for each page in reading order.
+fn spread_pages(
+    image: &ImageData,
+    companion: Option<&ImageData>,
+    right_to_left: bool,
+    width: f64,
+) -> Vec<(conrod_core::image::Id, f64, f64)> {
+    let companion = match companion {
+        Some(companion) => companion,
+        None => return vec![(image.id, 0.0, width)],
+    };
+
+    let [page_w, _] = spread_size(image.wh(), Some(companion.wh()));
+    let image_w = width * image.w as f64 / page_w;
+    let companion_w = width - image_w;
+    if right_to_left {
+        vec![
+            (image.id, companion_w, image_w),
+            (companion.id, 0.0, companion_w),
+        ]
+    } else {
+        vec![
+            (image.id, 0.0, image_w),
+            (companion.id, image_w, companion_w),
+        ]
+    }
+}
+
+/// Where the images are drawn partway through a transition.
+#[derive(Debug, PartialEq)]
+struct TransitionFrame {
+    /// The horizontal offset of the new image, or `None` if it's hidden.
+    incoming: Option<f64>,
+    /// The horizontal offset of the old image, or `None` if it's hidden.
+    outgoing: Option<f64>,
+    /// The opacity of black drawn over both images.
+    black: f64,
+}
+
+impl TransitionFrame {
+    fn new(transition: Transition, progress: f64, view_w: f64) -> Self {
+        let progress = progress.clamp(0.0, 1.0);
+        match transition {
+            Transition::None => TransitionFrame {
+                incoming: Some(0.0),
+                outgoing: None,
+                black: 0.0,
+            },
+            // The old image is faded by the image system, so it's drawn over the new one.
+            Transition::Crossfade => TransitionFrame {
+                incoming: Some(0.0),
+                outgoing: Some(0.0),
+                black: 0.0,
+            },
+            Transition::Slide => TransitionFrame {
+                incoming: Some((1.0 - progress) * view_w),
+                outgoing: Some(-progress * view_w),
+                black: 0.0,
+            },
+            Transition::FadeThroughBlack if progress < 0.5 => TransitionFrame {
+                incoming: None,
+                outgoing: Some(0.0),
+                black: progress * 2.0,
+            },
+            Transition::FadeThroughBlack => TransitionFrame {
+                incoming: Some(0.0),
+                outgoing: None,
+                black: (1.0 - progress) * 2.0,
+            },
+        }
+    }
+}
+
+/// The navigation used when the view can't pan any further.
+///
+/// Right to left spreads are read leftwards, so Left moves on to the next pages.
+fn pan_fallback(pan: e::Pan, is_right_to_left: bool) -> Option<e::Nav> {
+    match (pan, is_right_to_left) {
+        (e::Pan::Left, false) | (e::Pan::Right, true) | (e::Pan::PageUp, _) => {
+            Some(e::Nav::ImagePrev)
+        }
+        (e::Pan::Right, false) | (e::Pan::Left, true) | (e::Pan::PageDown, _) => {
+            Some(e::Nav::ImageNext)
+        }
+        (e::Pan::Up, _) | (e::Pan::Down, _) => None,
+    }
+}
+
+/// Scales geometrically so each step feels the same at any zoom level.
+///
+/// Touchpad pinch gestures are delivered as scrolling with CTRL held, so they get the finest steps.
 fn adjust_scale(scale: f64, scroll: &conrod_core::event::Scroll) -> f64 {
     use conrod_core::input::ModifierKey;
+    // One notch of a mouse wheel scrolls this far.
+    const POINTS_PER_STEP: f64 = 10.0;
+
+    let step = match scroll.modifiers {
+        ModifierKey::CTRL => 1.05,
+        ModifierKey::SHIFT => 1.5,
+        _ => ZOOM_STEP,
+    };
+
+    step_scale(scale, step, -scroll.y / POINTS_PER_STEP)
+}
+
+fn step_scale(scale: f64, step: f64, steps: f64) -> f64 {
     const MIN_SCALE: f64 = 0.01;
     const MAX_SCALE: f64 = 100.0;
 
-    let factor = match scroll.modifiers {
-        ModifierKey::CTRL => 1000.0,
-        ModifierKey::SHIFT => 250.0,
-        _ => 500.0,
-    };
+    let new_scale = scale * f64::powf(step, steps);
+    snap_scale(scale, new_scale).clamp(MIN_SCALE, MAX_SCALE)
+}
 
-    (scale + (scroll.y / -factor)).max(MIN_SCALE).min(MAX_SCALE)
+/// Stops at the first snap point between the scales in the direction of the zoom.
+fn snap_scale(old: f64, new: f64) -> f64 {
+    let is_between = |&snap: &f64| old.min(new) < snap && snap < old.max(new);
+    let mut snaps = SNAP_SCALES.iter().cloned();
+    if old < new {
+        snaps.find(is_between)
+    } else {
+        snaps.rev().find(is_between)
+    }
+    .unwrap_or(new)
 }
 
 struct ScaledImage {
@@ -173,26 +682,38 @@ struct ScaledImage {
 
 impl ScaledImage {
     fn new(
-        image: &ImageData,
+        [w, h]: [f64; 2],
         scale: &ImageScale,
+        upscale: bool,
         full_width: f64,
         full_height: f64,
     ) -> ScaledImage {
-        let w = image.w as f64;
-        let h = image.h as f64;
-
         match scale {
-            ImageScale::FitAll => {
-                let scale = (full_width / w).min(full_height / h);
+            ImageScale::Mode(mode) => {
+                let scale = match mode {
+                    ScaleMode::FitAll if upscale => (full_width / w).min(full_height / h),
+                    ScaleMode::FitAll => (full_width / w).min(full_height / h).min(1.0),
+                    ScaleMode::ActualSize => 1.0,
+                    ScaleMode::FitWidth => full_width / w,
+                    ScaleMode::FitHeight => full_height / h,
+                    ScaleMode::Fill => (full_width / w).max(full_height / h),
+                };
                 let w = scale * w;
                 let h = scale * h;
 
+                // Images taller than the view start at the top so they can be read downwards.
+                let top = if h > full_height && *mode == ScaleMode::FitWidth {
+                    0.0
+                } else {
+                    (full_height - h) / 2.0
+                };
+
                 ScaledImage {
                     scale,
                     w,
                     h,
                     left: (full_width - w) / 2.0,
-                    top: (full_height - h) / 2.0,
+                    top,
                 }
             }
             ImageScale::Scale {
@@ -212,4 +733,458 @@ impl ScaledImage {
             }
         }
     }
+
+    fn saved_view(&self, full_width: f64, full_height: f64) -> SavedView {
+        SavedView {
+            scale: self.scale,
+            center_x: (full_width / 2.0 - self.left) / self.w,
+            center_y: (full_height / 2.0 - self.top) / self.h,
+        }
+    }
+
+    /// The visible part of the image as `[left, top, right, bottom]` relative to the view.
+    fn visible_area(&self, full_width: f64, full_height: f64) -> [f64; 4] {
+        [
+            self.left.max(0.0),
+            self.top.max(0.0),
+            (self.left + self.w).min(full_width),
+            (self.top + self.h).min(full_height),
+        ]
+    }
+
+    /// Covers the visible part of the image with checkerboard sized tiles.
+    ///
+    /// Each tile is `[top, left, width, height]` relative to the view.
+    fn checkerboard_tiles(&self, full_width: f64, full_height: f64) -> Vec<[f64; 4]> {
+        let size = CHECKERBOARD_SIZE as f64;
+        let [x0, y0, x1, y1] = self.visible_area(full_width, full_height);
+
+        let mut tiles = Vec::new();
+        let mut top = y0;
+        while top < y1 {
+            let mut left = x0;
+            while left < x1 {
+                tiles.push([top, left, size.min(x1 - left), size.min(y1 - top)]);
+                left += size;
+            }
+            top += size;
+        }
+        tiles
+    }
+
+    /// The image pixel under `[x, y]` which is relative to the top left of the view.
+    fn pixel_at(&self, [x, y]: [f64; 2]) -> Option<[f64; 2]> {
+        let px = ((x - self.left) / self.scale).floor();
+        let py = ((y - self.top) / self.scale).floor();
+        let is_inside =
+            px >= 0.0 && py >= 0.0 && px < self.w / self.scale && py < self.h / self.scale;
+        if is_inside {
+            Some([px, py])
+        } else {
+            None
+        }
+    }
+
+    /// Paths along the pixel boundaries of the visible part of the image.
+    ///
+    /// Each path zigzags across the image so a single path can draw all of the lines in
+    /// one direction; the connecting segments lie along the edges of the visible area.
+    fn grid(&self, full_width: f64, full_height: f64) -> [Vec<[f64; 2]>; 2] {
+        let [x0, y0, x1, y1] = self.visible_area(full_width, full_height);
+
+        let boundaries = |start: f64, end: f64, offset: f64| {
+            let first = ((start - offset) / self.scale).ceil() as i64;
+            let last = ((end - offset) / self.scale).floor() as i64;
+            (first..=last).map(move |i| offset + i as f64 * self.scale)
+        };
+
+        let mut columns = Vec::new();
+        for (i, x) in boundaries(x0, x1, self.left).enumerate() {
+            let (from, to) = if i % 2 == 0 { (y0, y1) } else { (y1, y0) };
+            columns.push([x, from]);
+            columns.push([x, to]);
+        }
+
+        let mut rows = Vec::new();
+        for (i, y) in boundaries(y0, y1, self.top).enumerate() {
+            let (from, to) = if i % 2 == 0 { (x0, x1) } else { (x1, x0) };
+            rows.push([from, y]);
+            rows.push([to, y]);
+        }
+
+        [columns, rows]
+    }
+
+    /// Changes the scale while keeping the image point under `[x, y]` in place.
+    ///
+    /// The point is relative to the top left of the view.
+    fn zoom_at(&self, scale: f64, [x, y]: [f64; 2]) -> ImageScale {
+        let ratio = scale / self.scale;
+        ImageScale::Scale {
+            scale,
+            offset_top: y - (y - self.top) * ratio,
+            offset_left: x - (x - self.left) * ratio,
+        }
+    }
+
+    /// Moves the view across the image without leaving its edges.
+    ///
+    /// Returns `None` if the view is already at the edge in that direction.
+    fn pan(&self, pan: e::Pan, full_width: f64, full_height: f64) -> Option<ImageScale> {
+        const EDGE: f64 = 0.5;
+        let step_x = full_width * PAN_FRACTION;
+        let step_y = full_height * PAN_FRACTION;
+        let min_left = full_width - self.w;
+        let min_top = full_height - self.h;
+        let can_pan_up = self.top < -EDGE;
+        let can_pan_down = self.top > min_top + EDGE;
+
+        let (left, top) = match pan {
+            e::Pan::Left if self.left < -EDGE => ((self.left + step_x).min(0.0), self.top),
+            e::Pan::Right if self.left > min_left + EDGE => {
+                ((self.left - step_x).max(min_left), self.top)
+            }
+            e::Pan::Up if can_pan_up => (self.left, (self.top + step_y).min(0.0)),
+            e::Pan::Down if can_pan_down => (self.left, (self.top - step_y).max(min_top)),
+            e::Pan::PageUp if can_pan_up => (self.left, (self.top + full_height).min(0.0)),
+            e::Pan::PageDown if can_pan_down => (self.left, (self.top - full_height).max(min_top)),
+            _ => return None,
+        };
+
+        Some(ImageScale::Scale {
+            scale: self.scale,
+            offset_top: top,
+            offset_left: left,
+        })
+    }
+}
+
+/// A small overview of the whole image in the top right of the view.
+struct Minimap {
+    /// The minimap's size relative to the actual image.
+    scale: f64,
+    w: f64,
+    h: f64,
+    top: f64,
+    left: f64,
+}
+
+impl Minimap {
+    /// Returns `None` when the whole image is already visible.
+    fn new(
+        [w, h]: [f64; 2],
+        scaled: &ScaledImage,
+        full_width: f64,
+        full_height: f64,
+    ) -> Option<Minimap> {
+        const EDGE: f64 = 0.5;
+        let is_visible = scaled.left >= -EDGE
+            && scaled.top >= -EDGE
+            && scaled.left + scaled.w <= full_width + EDGE
+            && scaled.top + scaled.h <= full_height + EDGE;
+        if is_visible {
+            return None;
+        }
+
+        let scale = (MINIMAP_SIZE / w).min(MINIMAP_SIZE / h);
+        let w = w * scale;
+        let h = h * scale;
+        Some(Minimap {
+            scale,
+            w,
+            h,
+            top: MINIMAP_MARGIN,
+            left: full_width - MINIMAP_MARGIN - w,
+        })
+    }
+
+    /// Whether `[x, y]`, relative to the top left of the view, is over the minimap.
+    fn contains(&self, [x, y]: [f64; 2]) -> bool {
+        x >= self.left && x <= self.left + self.w && y >= self.top && y <= self.top + self.h
+    }
+
+    /// The visible part of the image on the minimap as `[top, left, width, height]`
+    /// relative to the view.
+    fn view_rect(&self, scaled: &ScaledImage, full_width: f64, full_height: f64) -> [f64; 4] {
+        let [x0, y0, x1, y1] = scaled.visible_area(full_width, full_height);
+        let ratio = self.scale / scaled.scale;
+        [
+            self.top + (y0 - scaled.top) * ratio,
+            self.left + (x0 - scaled.left) * ratio,
+            (x1 - x0) * ratio,
+            (y1 - y0) * ratio,
+        ]
+    }
+
+    /// Centers the view on the image point under `[x, y]` on the minimap.
+    fn center_on(
+        &self,
+        scaled: &ScaledImage,
+        [x, y]: [f64; 2],
+        full_width: f64,
+        full_height: f64,
+    ) -> ImageScale {
+        let ratio = scaled.scale / self.scale;
+        ImageScale::Scale {
+            scale: scaled.scale,
+            offset_top: full_height / 2.0 - (y - self.top) * ratio,
+            offset_left: full_width / 2.0 - (x - self.left) * ratio,
+        }
+    }
+
+    /// Moves the view along with a drag across the minimap.
+    fn drag(&self, scaled: &ScaledImage, [dx, dy]: [f64; 2]) -> ImageScale {
+        let ratio = scaled.scale / self.scale;
+        ImageScale::Scale {
+            scale: scaled.scale,
+            offset_top: scaled.top + dy * ratio,
+            offset_left: scaled.left - dx * ratio,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image_at(scale: f64, top: f64, left: f64) -> ScaledImage {
+        ScaledImage {
+            scale,
+            w: 100.0 * scale,
+            h: 100.0 * scale,
+            top,
+            left,
+        }
+    }
+
+    #[test]
+    pub fn zoom_at_keeps_point_under_cursor() {
+        let image = image_at(1.0, 10.0, 20.0);
+        let cursor = [70.0, 60.0];
+
+        match image.zoom_at(2.0, cursor) {
+            ImageScale::Scale {
+                scale,
+                offset_top,
+                offset_left,
+            } => {
+                assert_eq!(scale, 2.0);
+                // The cursor was over image pixel (50, 50) which is now 100 points from the edge.
+                assert_eq!(offset_left, 70.0 - 100.0);
+                assert_eq!(offset_top, 60.0 - 100.0);
+            }
+            _ => panic!("zooming should produce a free scale"),
+        }
+    }
+
+    #[test]
+    pub fn pan_stays_within_image() {
+        // A 200x200 image in a 100x100 view, scrolled to the top left corner.
+        let image = image_at(2.0, 0.0, 0.0);
+
+        assert!(image.pan(e::Pan::Left, 100.0, 100.0).is_none());
+        assert!(image.pan(e::Pan::Up, 100.0, 100.0).is_none());
+        assert!(image.pan(e::Pan::PageUp, 100.0, 100.0).is_none());
+
+        match image.pan(e::Pan::Right, 100.0, 100.0) {
+            Some(ImageScale::Scale { offset_left, .. }) => assert_eq!(offset_left, -12.5),
+            _ => panic!("should pan right"),
+        }
+        match image.pan(e::Pan::PageDown, 100.0, 100.0) {
+            Some(ImageScale::Scale { offset_top, .. }) => assert_eq!(offset_top, -100.0),
+            _ => panic!("should page down"),
+        }
+
+        let image = image_at(2.0, -100.0, -95.0);
+        match image.pan(e::Pan::Right, 100.0, 100.0) {
+            Some(ImageScale::Scale { offset_left, .. }) => assert_eq!(offset_left, -100.0),
+            _ => panic!("should pan right to the edge"),
+        }
+        assert!(image.pan(e::Pan::Down, 100.0, 100.0).is_none());
+        assert!(image.pan(e::Pan::PageDown, 100.0, 100.0).is_none());
+    }
+
+    #[test]
+    pub fn pan_falls_back_when_image_fits() {
+        let image = image_at(0.5, 25.0, 25.0);
+
+        for pan in &[e::Pan::Left, e::Pan::Right, e::Pan::Up, e::Pan::Down] {
+            assert!(image.pan(*pan, 100.0, 100.0).is_none(), "{:?}", pan);
+        }
+
+        assert!(matches!(
+            pan_fallback(e::Pan::Left, false),
+            Some(e::Nav::ImagePrev)
+        ));
+        assert!(matches!(
+            pan_fallback(e::Pan::Left, true),
+            Some(e::Nav::ImageNext)
+        ));
+        assert!(matches!(
+            pan_fallback(e::Pan::PageDown, true),
+            Some(e::Nav::ImageNext)
+        ));
+    }
+
+    #[test]
+    pub fn saved_view_keeps_relative_position() {
+        // A 100x100 image at 200% with its center in the middle of a 100x100 view.
+        let view = image_at(2.0, -50.0, -50.0).saved_view(100.0, 100.0);
+        assert_eq!(view.center_x, 0.5);
+        assert_eq!(view.center_y, 0.5);
+
+        match restore_view(&view, [200.0, 50.0], 100.0, 100.0) {
+            ImageScale::Scale {
+                scale,
+                offset_top,
+                offset_left,
+            } => {
+                assert_eq!(scale, 2.0);
+                assert_eq!(offset_left, 50.0 - 200.0);
+                assert_eq!(offset_top, 50.0 - 50.0);
+            }
+            _ => panic!("restoring should produce a free scale"),
+        }
+    }
+
+    #[test]
+    pub fn pixel_at_finds_hovered_pixel() {
+        let image = image_at(10.0, 5.0, 5.0);
+
+        assert_eq!(image.pixel_at([5.0, 5.0]), Some([0.0, 0.0]));
+        assert_eq!(image.pixel_at([34.0, 16.0]), Some([2.0, 1.0]));
+        assert_eq!(image.pixel_at([1004.9, 1004.9]), Some([99.0, 99.0]));
+        assert_eq!(image.pixel_at([4.0, 50.0]), None);
+        assert_eq!(image.pixel_at([50.0, 1005.0]), None);
+    }
+
+    #[test]
+    pub fn grid_covers_visible_pixels() {
+        // 100x100 pixels at 10x, so only a 5x3 pixel area is visible.
+        let image = image_at(10.0, 0.0, -5.0);
+        let [columns, rows] = image.grid(50.0, 30.0);
+
+        let xs: Vec<f64> = columns.iter().step_by(2).map(|p| p[0]).collect();
+        assert_eq!(xs, vec![5.0, 15.0, 25.0, 35.0, 45.0]);
+        assert_eq!(columns[1], [5.0, 30.0]);
+        assert_eq!(columns[2], [15.0, 30.0]);
+
+        let ys: Vec<f64> = rows.iter().step_by(2).map(|p| p[1]).collect();
+        assert_eq!(ys, vec![0.0, 10.0, 20.0, 30.0]);
+    }
+
+    #[test]
+    pub fn checkerboard_tiles_cover_visible_area() {
+        let size = CHECKERBOARD_SIZE as f64;
+        let image = image_at(1.0, -10.0, 20.0);
+        let tiles = image.checkerboard_tiles(size * 2.0, size);
+
+        // 100x100 image with 90x100 visible so a single tile is needed.
+        assert_eq!(tiles, vec![[0.0, 20.0, 100.0, 90.0]]);
+
+        let image = image_at(5.0, 0.0, 0.0);
+        let tiles = image.checkerboard_tiles(size + 10.0, size + 10.0);
+        assert_eq!(
+            tiles,
+            vec![
+                [0.0, 0.0, size, size],
+                [0.0, size, 10.0, size],
+                [size, 0.0, size, 10.0],
+                [size, size, 10.0, 10.0],
+            ]
+        );
+    }
+
+    #[test]
+    pub fn minimap_follows_view() {
+        let data = [100.0, 100.0];
+        assert!(Minimap::new(data, &image_at(1.0, 0.0, 0.0), 100.0, 100.0).is_none());
+
+        // A 400x400 image in a 100x100 view showing its center.
+        let image = image_at(4.0, -150.0, -150.0);
+        let minimap = Minimap::new(data, &image, 100.0, 100.0).expect("image is not visible");
+        assert_eq!(minimap.left, 100.0 - MINIMAP_MARGIN - MINIMAP_SIZE);
+        let quarter = MINIMAP_SIZE / 4.0;
+        let [top, left, w, h] = minimap.view_rect(&image, 100.0, 100.0);
+        assert_eq!([top - minimap.top, left - minimap.left], [quarter * 1.5; 2]);
+        assert_eq!([w, h], [quarter, quarter]);
+
+        match minimap.center_on(&image, [minimap.left, minimap.top], 100.0, 100.0) {
+            ImageScale::Scale {
+                offset_top,
+                offset_left,
+                ..
+            } => assert_eq!([offset_top, offset_left], [50.0, 50.0]),
+            _ => panic!("centering should produce a free scale"),
+        }
+
+        // Dragging the view down and right moves the image up and left.
+        match minimap.drag(&image, [quarter, -quarter]) {
+            ImageScale::Scale {
+                offset_top,
+                offset_left,
+                ..
+            } => assert_eq!([offset_top, offset_left], [-250.0, -250.0]),
+            _ => panic!("dragging should produce a free scale"),
+        }
+    }
+
+    #[test]
+    pub fn spread_size_matches_page_heights() {
+        assert_eq!(spread_size([100.0, 200.0], None), [100.0, 200.0]);
+        assert_eq!(
+            spread_size([100.0, 200.0], Some([50.0, 100.0])),
+            [200.0, 200.0]
+        );
+        assert_eq!(
+            spread_size([100.0, 200.0], Some([300.0, 400.0])),
+            [250.0, 200.0]
+        );
+    }
+
+    #[test]
+    pub fn snap_scale_stops_at_snap_points() {
+        assert_eq!(snap_scale(0.9, 1.1), 1.0);
+        assert_eq!(snap_scale(1.1, 0.9), 1.0);
+        assert_eq!(snap_scale(1.0, 1.2), 1.2);
+        assert_eq!(snap_scale(1.0, 0.8), 0.8);
+        assert_eq!(snap_scale(0.3, 0.4), 0.4);
+        assert_eq!(snap_scale(0.3, 3.0), 0.5);
+        assert_eq!(snap_scale(3.0, 0.3), 2.0);
+    }
+
+    #[test]
+    pub fn transition_frames_hand_over_between_images() {
+        let frame = |transition, progress| TransitionFrame::new(transition, progress, 100.0);
+
+        assert_eq!(
+            frame(Transition::Slide, 0.25),
+            TransitionFrame {
+                incoming: Some(75.0),
+                outgoing: Some(-25.0),
+                black: 0.0,
+            }
+        );
+        assert_eq!(
+            frame(Transition::FadeThroughBlack, 0.25),
+            TransitionFrame {
+                incoming: None,
+                outgoing: Some(0.0),
+                black: 0.5,
+            }
+        );
+        assert_eq!(
+            frame(Transition::FadeThroughBlack, 0.75),
+            TransitionFrame {
+                incoming: Some(0.0),
+                outgoing: None,
+                black: 0.5,
+            }
+        );
+        assert_eq!(
+            frame(Transition::Slide, 1.5),
+            frame(Transition::Slide, 1.0),
+            "progress is clamped"
+        );
+    }
 }
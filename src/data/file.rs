@@ -1,8 +1,11 @@
 use std::convert::{From, Into};
 use std::fmt;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 
+use super::{ImageInfo, SavedView};
+use crate::support::{ErrToString, ExtensionIs};
+
 #[derive(Debug, Clone, Ord, PartialOrd, Eq, PartialEq)]
 pub struct Rating(usize);
 
@@ -71,6 +74,30 @@ impl fmt::Display for Rating {
 pub struct File {
     pub path: PathBuf,
     pub rating: Option<Rating>,
+    /// The zoom and position remembered for this file.
+    pub view: Option<SavedView>,
+    /// The header info cached for this file if it's been read, or why it couldn't be.
+    pub info: Option<Result<ImageInfo, String>>,
+    /// The size and modification time read when the directory was opened.
+    pub metadata: Option<FileMetadata>,
+}
+
+/// What's needed from a file's metadata, read once so filtering and sorting don't each check
+/// every file on disk.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct FileMetadata {
+    pub size: u64,
+    pub modified: SystemTime,
+}
+
+impl FileMetadata {
+    pub fn read(path: &Path) -> Option<FileMetadata> {
+        let metadata = path.metadata().ok()?;
+        Some(FileMetadata {
+            size: metadata.len(),
+            modified: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+        })
+    }
 }
 
 impl From<&str> for File {
@@ -78,6 +105,9 @@ impl From<&str> for File {
         File {
             path: PathBuf::from(path),
             rating: None,
+            view: None,
+            info: None,
+            metadata: None,
         }
     }
 }
@@ -91,14 +121,72 @@ impl File {
     }
 
     pub fn size(&self) -> FileSize {
-        FileSize(self.path.metadata().map(|m| m.len()).unwrap_or(0))
+        match self.metadata {
+            Some(metadata) => FileSize(metadata.size),
+            None => FileSize(self.path.metadata().map(|m| m.len()).unwrap_or(0)),
+        }
     }
 
     pub fn last_modified(&self) -> SystemTime {
-        self.path
-            .metadata()
-            .map(|m| m.modified().unwrap_or(SystemTime::UNIX_EPOCH))
-            .unwrap_or(SystemTime::UNIX_EPOCH)
+        match self.metadata {
+            Some(metadata) => metadata.modified,
+            None => self
+                .path
+                .metadata()
+                .map(|m| m.modified().unwrap_or(SystemTime::UNIX_EPOCH))
+                .unwrap_or(SystemTime::UNIX_EPOCH),
+        }
+    }
+
+    /// Reads the image's width and height from its header without decoding it.
+    pub fn dimensions(&self) -> Result<(u32, u32), String> {
+        use image::ImageDecoder;
+        use std::io::BufReader;
+
+        if let Some(info) = &self.info {
+            return info.clone().map(|info| (info.width, info.height));
+        }
+
+        let reader = BufReader::new(std::fs::File::open(&self.path).err_to_string()?);
+        let path = &self.path;
+        if path.extension_is("png") {
+            image::png::PNGDecoder::new(reader).dimensions()
+        } else if path.extension_is("jpg") || path.extension_is("jpeg") {
+            image::jpeg::JPEGDecoder::new(reader).dimensions()
+        } else if path.extension_is("gif") {
+            image::gif::Decoder::new(reader).dimensions()
+        } else if path.extension_is("bmp") {
+            image::bmp::BMPDecoder::new(reader).dimensions()
+        } else if path.extension_is("webp") {
+            // The `image` crate only reads the headers of lossy WebP files.
+            return ImageInfo::read(path).map(|info| (info.width, info.height));
+        } else {
+            return Err(format!("Unsupported image type: {}", path.display()));
+        }
+        .err_to_string()
+    }
+
+    /// The image's header info, which is unknown until it's been read in the background.
+    pub fn image_info(&self) -> Result<ImageInfo, String> {
+        match &self.info {
+            Some(info) => info.clone(),
+            None => Err(format!("Image info not read yet: {}", self.path.display())),
+        }
+    }
+
+    /// Whether the image is wider than it is tall, so it fills a two page spread by itself.
+    pub fn is_wide(&self) -> bool {
+        match self.dimensions() {
+            Ok((w, h)) => w > h,
+            Err(e) => {
+                log::warn!(
+                    "Could not read dimensions of {}: {}",
+                    self.path.display(),
+                    e
+                );
+                false
+            }
+        }
     }
 }
 
@@ -125,6 +213,10 @@ pub enum FileSizeUnit {
 }
 
 impl FileSize {
+    pub fn bytes(&self) -> u64 {
+        self.0
+    }
+
     pub fn reduce(&self) -> FileSizeUnit {
         if self.0 < 1024 {
             return FileSizeUnit::Byte(self.0);
@@ -1,11 +1,14 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::convert::AsRef;
 use std::fmt;
 use std::ops::Drop;
 use std::path::{Path, PathBuf};
-use std::time::{Duration, Instant};
 
-use super::persist::PersistenceManager;
-use super::{File, Filter, Rating};
+use super::persist::{DirectoryState, PersistenceManager, SettingsManager};
+use super::{
+    File, FileMetadata, Filter, ImageInfo, Layout, Rating, SavedFilter, SavedView, ViewSettings,
+};
 use crate::support::{ExtensionIs, LogError, ToNone};
 use crate::systems::EventSystem;
 
@@ -17,13 +20,32 @@ pub struct FileList {
     filter: Filter,
     filtered_files: Vec<File>,
     persist: Option<PersistenceManager>,
-    slideshow: Option<Duration>,
-    slideshow_last_update: Instant,
+    /// The filters saved for this directory and then every directory, with how many files each
+    /// matches once they're counted.
+    saved_filters: Vec<(SavedFilter, Option<usize>)>,
+    /// Whether the saved filters have been shown, until which filters needing image info aren't
+    /// counted as that reads every file.
+    are_counts_shown: bool,
+    /// Whether each file paired up in a spread is wide, as spreads are checked on every
+    /// navigation and reading the header each time would be slow.
+    wide_files: RefCell<HashMap<PathBuf, bool>>,
+    /// Whether the current file, sort or filter changed since the directory was opened, which
+    /// is saved when it's closed.
+    is_state_changed: bool,
+    /// Whether the current file was sent to be loaded, which the continuous layout skips.
+    is_current_loaded: bool,
+    /// Whether the files without image info have been sent to be read in the background.
+    is_image_info_requested: bool,
+    /// The files to send to be read on the next update.
+    image_info_requests: Vec<PathBuf>,
 }
 
 impl Drop for FileList {
     fn drop(&mut self) {
         use std::mem::replace;
+        if self.is_state_changed {
+            self.save_state();
+        }
         if let Some(p) = replace(&mut self.persist, None) {
             if let Err(e) = p.close() {
                 log::error!("Failed to close persistence manager: {}", e);
@@ -81,33 +103,32 @@ impl FileList {
         }
 
         let dir = path.parent()?;
-        let mut list = FileList::from_dir(dir)?;
-
-        let (i, _) = list
-            .files
-            .iter()
-            .enumerate()
-            .find(|(_, f)| f.path == path)?;
-        list.current_index = i;
-
-        Some(list)
+        FileList::open_dir(dir, Some(path))
     }
 
     pub fn from_dir(path: &Path) -> Option<Self> {
+        FileList::open_dir(path, None)
+    }
+
+    /// Opens a directory at `file`, or where it was last left off if there isn't one.
+    fn open_dir(path: &Path, file: Option<&Path>) -> Option<Self> {
         if !path.exists() || !path.is_dir() {
             return None;
         }
 
         let mut file_names = Vec::new();
         for entry in path.read_dir().ok()? {
-            let entry = entry.ok()?;
-            if !is_image_file(&entry.path()) {
+            let file_path = entry.ok()?.path();
+            if !is_image_file(&file_path) {
                 continue;
             }
 
             file_names.push(File {
-                path: entry.path(),
+                metadata: FileMetadata::read(&file_path),
+                path: file_path,
                 rating: None,
+                view: None,
+                info: None,
             });
         }
 
@@ -119,11 +140,35 @@ impl FileList {
                 )
             })
             .ok();
+        let mut state = DirectoryState::default();
+        let mut saved_filters = Vec::new();
         if let Some(persist) = &persist {
             persist.populate_files(&mut file_names).log_err();
+            if let Some(saved) = persist.directory_state().log_err() {
+                state = saved;
+            }
+            saved_filters = persist.saved_filters().log_err().unwrap_or_default();
+        }
+
+        let mut list = FileList::from_files(file_names, persist);
+        list.add_saved_filters(saved_filters);
+        list.restore(state.sort.unwrap_or(FileSort::Name), state.filter);
+
+        match file {
+            Some(file) => {
+                if !list.select(file) {
+                    return None;
+                }
+            }
+            None => {
+                if let Some(last_file) = state.last_file {
+                    log::info!("Resuming directory at {}", last_file);
+                    list.select(&path.join(last_file));
+                }
+            }
         }
 
-        Some(FileList::from_files(file_names, persist))
+        Some(list)
     }
 
     pub fn from_files(files: Vec<File>, persist: Option<PersistenceManager>) -> Self {
@@ -134,8 +179,13 @@ impl FileList {
             filter: Filter::default(),
             filtered_files: Vec::new(),
             persist,
-            slideshow: None,
-            slideshow_last_update: Instant::now(),
+            saved_filters: Vec::new(),
+            wide_files: RefCell::new(HashMap::new()),
+            is_state_changed: false,
+            is_current_loaded: true,
+            is_image_info_requested: false,
+            image_info_requests: Vec::new(),
+            are_counts_shown: false,
         };
 
         list.apply_sort();
@@ -161,10 +211,24 @@ impl FileList {
         &self.current_sort
     }
 
+    pub fn filter(&self) -> &Filter {
+        &self.filter
+    }
+
     pub fn len(&self) -> usize {
         self.files.len()
     }
 
+    /// The saved filters with how many files each matches, or `None` if not counted yet.
+    pub fn saved_filters(&self) -> &[(SavedFilter, Option<usize>)] {
+        &self.saved_filters
+    }
+
+    /// The number of files including those hidden by the filter.
+    pub fn total_len(&self) -> usize {
+        self.files.len() + self.filtered_files.len()
+    }
+
     fn set_current(&mut self, current: usize) -> Option<&File> {
         let i = if self.files.len() == 0 {
             0
@@ -181,36 +245,136 @@ impl FileList {
     }
 
     fn set_rating(&mut self, rating: Option<Rating>) {
-        if let Some(current) = self.current_mut() {
-            current.rating = rating;
-        }
+        let before = match self.current_mut() {
+            Some(current) => {
+                let before = current.clone();
+                current.rating = rating;
+                before
+            }
+            None => return,
+        };
 
         match (&self.current(), &self.persist) {
             (Some(current), Some(persist)) => persist.set_rating(current, &current.rating),
             _ => Ok(()),
         }
         .log_err();
+
+        if let Some(after) = self.current().cloned() {
+            self.recount_saved_filters(&[(before, after)]);
+        }
     }
 
-    fn next(&mut self) -> Option<&File> {
-        self.set_current(self.current_index + 1)
+    fn set_view(&mut self, path: &Path, view: Option<SavedView>) {
+        let file = self
+            .files
+            .iter_mut()
+            .chain(self.filtered_files.iter_mut())
+            .find(|f| f.path == path);
+
+        if let Some(file) = file {
+            file.view = view;
+            if let Some(persist) = &self.persist {
+                persist.set_view(file, &file.view).log_err();
+            }
+        }
+    }
+
+    /// Makes `path` the current file, clearing the filter if it hides the file.
+    fn select(&mut self, path: &Path) -> bool {
+        let is_filtered = self.filtered_files.iter().any(|f| f.path == path);
+        if is_filtered {
+            log::info!("Clearing filter to show {}", path.display());
+            self.apply_filter(Filter::default());
+        }
+
+        match self.files.iter().position(|f| f.path == path) {
+            Some(i) => {
+                self.current_index = i;
+                true
+            }
+            None => false,
+        }
     }
 
-    fn prev(&mut self) -> Option<&File> {
+    /// Saves where the directory was left off, which is done once as it's closed.
+    fn save_state(&self) {
+        if let Some(persist) = &self.persist {
+            let state = DirectoryState {
+                last_file: self.current().map(File::name),
+                sort: Some(self.current_sort),
+                filter: self.filter.clone(),
+            };
+            persist.set_directory_state(&state).log_err();
+        }
+    }
+
+    fn next(&mut self, view: &ViewSettings) -> Option<&File> {
+        let step = match self.companion_index(self.current_index, view) {
+            Some(_) => 2,
+            None => 1,
+        };
+        self.set_current(self.current_index + step)
+    }
+
+    fn prev(&mut self, view: &ViewSettings) -> Option<&File> {
         let i = if self.current_index > 0 {
             self.current_index
         } else {
             self.len().max(1)
         } - 1;
+
+        // Step back a whole spread if the previous file is the second page of one.
+        let i = match i.checked_sub(1) {
+            Some(before) if self.companion_index(before, view) == Some(i) => before,
+            _ => i,
+        };
         self.set_current(i)
     }
 
+    /// The file shown beside the current one in a two page spread.
+    pub fn companion(&self, view: &ViewSettings) -> Option<&File> {
+        self.companion_index(self.current_index, view)
+            .and_then(|i| self.get_file(i))
+    }
+
+    fn companion_index(&self, index: usize, view: &ViewSettings) -> Option<usize> {
+        if view.layout != Layout::Spread || (view.single_first_page && index == 0) {
+            return None;
+        }
+
+        let page = self.get_file(index)?;
+        let next = self.get_file(index + 1)?;
+        if self.is_wide(page) || self.is_wide(next) {
+            None
+        } else {
+            Some(index + 1)
+        }
+    }
+
+    fn is_wide(&self, file: &File) -> bool {
+        *self
+            .wide_files
+            .borrow_mut()
+            .entry(file.path.clone())
+            .or_insert_with(|| file.is_wide())
+    }
+
+    /// Whether the last file is shown, either on its own or as the current file's companion.
+    pub fn is_last(&self, view: &ViewSettings) -> bool {
+        let shown = self
+            .companion_index(self.current_index, view)
+            .unwrap_or(self.current_index);
+        shown + 1 >= self.len()
+    }
+
     pub fn get_file(&self, index: usize) -> Option<&File> {
         self.files.get(index)
     }
 
     fn sort_by(&mut self, property: FileSort) {
-        if self.current_sort == property {
+        // Sorting randomly again reshuffles the files.
+        if self.current_sort == property && property != FileSort::Random {
             log::info!("Sort files by {} skipped due to already sorted", property);
             return;
         }
@@ -237,6 +401,13 @@ impl FileList {
             }
         }
 
+        if self.filter.is_ranked() {
+            // The sort is stable, so files which match equally well stay in sorted order.
+            let filter = &self.filter;
+            self.files
+                .sort_by_cached_key(|f| std::cmp::Reverse(filter.score(f)));
+        }
+
         let new_idx = if let Some(selected) = selected {
             self.files
                 .iter()
@@ -253,63 +424,266 @@ impl FileList {
     }
 
     fn apply_filter(&mut self, filter: Filter) {
+        let is_reset = !filter.is_subset_of(&self.filter);
+        self.filter_files(filter, is_reset);
+    }
+
+    /// Filters every file again, as files whose image info has arrived may match now.
+    fn reapply_filter(&mut self) {
+        let filter = self.filter.clone();
+        self.filter_files(filter, true);
+    }
+
+    fn filter_files(&mut self, filter: Filter, is_reset: bool) {
         log::info!("Filtering files: {:?}", filter);
 
-        let is_subset = filter.is_subset_of(&self.filter);
-        if !is_subset {
+        if is_reset {
             log::info!("Resetting file list for filtering");
             while let Some(f) = self.filtered_files.pop() {
                 self.files.push(f);
             }
         }
 
-        let mut i = 0;
-        while i < self.files.len() {
-            let file = &self.files[i];
-            if !filter.matches(file) {
-                log::info!("Filtering out file: {}", file.path.display());
-                let f = self.files.remove(i);
-                self.filtered_files.push(f);
-                if self.current_index > 0 && self.current_index > i {
+        if filter.needs_image_info() {
+            self.request_image_info();
+        }
+
+        // Files are moved in one pass rather than removed one at a time, which is quadratic.
+        let current_index = self.current_index;
+        let mut kept = Vec::with_capacity(self.files.len());
+        for (i, file) in self.files.drain(..).enumerate() {
+            if filter.matches(&file) {
+                kept.push(file);
+            } else {
+                log::debug!("Filtering out file: {}", file.path.display());
+                if i < current_index {
                     self.current_index -= 1;
                 }
-            } else {
-                i += 1;
+                self.filtered_files.push(file);
             }
         }
+        self.files = kept;
 
+        // Nothing is current when the filter matches no files.
         if self.current_index >= self.len() {
-            self.current_index = self.len() - 1;
+            self.current_index = self.len().saturating_sub(1);
         }
 
         self.filter = filter;
-        if !is_subset {
+        if is_reset || self.filter.is_ranked() {
             self.apply_sort();
         }
     }
 
-    pub fn is_slideshow_enabled(&self) -> bool {
-        self.slideshow.is_some()
+    /// Sends the files which don't have their image info cached to be read in the background.
+    ///
+    /// This only happens once per directory, until then filters treat the info as unknown.
+    fn request_image_info(&mut self) {
+        if self.is_image_info_requested {
+            return;
+        }
+        self.is_image_info_requested = true;
+
+        self.image_info_requests = self
+            .files
+            .iter()
+            .chain(&self.filtered_files)
+            .filter(|f| f.info.is_none())
+            .map(|f| f.path.clone())
+            .collect();
+        if !self.image_info_requests.is_empty() {
+            log::info!(
+                "Reading image info of {} files",
+                self.image_info_requests.len()
+            );
+        }
+    }
+
+    /// Stores image info read in the background and saves it for next time, including which
+    /// files couldn't be read.
+    fn set_image_info(&mut self, infos: &[(PathBuf, Result<ImageInfo, String>)]) {
+        let infos: HashMap<&Path, &Result<ImageInfo, String>> = infos
+            .iter()
+            .map(|(path, info)| (path.as_path(), info))
+            .collect();
+
+        let mut changed = Vec::new();
+        for file in self.files.iter_mut().chain(&mut self.filtered_files) {
+            if let Some(&info) = infos.get(file.path.as_path()) {
+                if let Err(e) = info {
+                    log::warn!(
+                        "Could not read image info of {}: {}",
+                        file.path.display(),
+                        e
+                    );
+                }
+                let before = file.clone();
+                file.info = Some(info.clone());
+                changed.push((before, file.clone()));
+            }
+        }
+
+        if changed.is_empty() {
+            return;
+        }
+
+        if let Some(persist) = &mut self.persist {
+            let read: Vec<_> = changed.iter().map(|(_, after)| after.clone()).collect();
+            persist.set_image_info(&read).log_err();
+        }
+        if self.filter.needs_image_info() {
+            self.reapply_filter();
+        }
+        self.recount_saved_filters(&changed);
+    }
+
+    /// Adds filters loaded from the user's settings, which are kept after this directory's own.
+    pub fn add_global_filters(&mut self, settings: &SettingsManager) {
+        if let Some(saved) = settings.saved_filters().log_err() {
+            self.add_saved_filters(saved);
+        }
     }
 
-    fn slideshow_start(&mut self, duration: Duration) {
-        self.slideshow = Some(duration);
-        self.slideshow_last_update = Instant::now();
+    fn add_saved_filters(&mut self, saved: Vec<SavedFilter>) {
+        self.saved_filters
+            .extend(saved.into_iter().map(|saved| (saved, None)));
+        self.count_saved_filters();
     }
 
-    fn slideshow_stop(&mut self) {
-        self.slideshow = None;
+    /// Counts the files each saved filter matches.
+    ///
+    /// Filters needing image info aren't counted until the saved filters are shown, as that's
+    /// when the image info is read.
+    fn count_saved_filters(&mut self) {
+        if self.are_counts_shown
+            && self
+                .saved_filters
+                .iter()
+                .any(|(saved, _)| saved.filter.needs_image_info())
+        {
+            self.request_image_info();
+        }
+
+        let files = &self.files;
+        let filtered_files = &self.filtered_files;
+        let are_counts_shown = self.are_counts_shown;
+        for (saved, count) in &mut self.saved_filters {
+            *count = if are_counts_shown || !saved.filter.needs_image_info() {
+                let matching = files
+                    .iter()
+                    .chain(filtered_files)
+                    .filter(|f| saved.filter.matches(f))
+                    .count();
+                Some(matching)
+            } else {
+                None
+            };
+        }
+    }
+
+    /// Updates the counts for files which changed from how they were before, such as by being
+    /// rated or having their image info read, without going over every file again.
+    fn recount_saved_filters(&mut self, changed: &[(File, File)]) {
+        for (saved, count) in &mut self.saved_filters {
+            let count = match count {
+                Some(count) => count,
+                None => continue,
+            };
+            for (before, after) in changed {
+                match (saved.filter.matches(before), saved.filter.matches(after)) {
+                    (false, true) => *count += 1,
+                    (true, false) => *count -= 1,
+                    _ => (),
+                }
+            }
+        }
+    }
+
+    /// Counts every saved filter the first time they're shown.
+    fn show_saved_filters(&mut self) {
+        if !self.are_counts_shown {
+            self.are_counts_shown = true;
+            self.count_saved_filters();
+        }
     }
 
-    pub fn update(&mut self, events: &mut EventSystem) {
+    /// Saves the current filter, replacing any saved filter with the same name.
+    fn save_filter(&mut self, name: &str, is_global: bool, settings: Option<&SettingsManager>) {
+        let saved = SavedFilter {
+            name: name.to_owned(),
+            filter: self.filter.clone(),
+            is_global,
+        };
+        let result = match (is_global, &self.persist, settings) {
+            (false, Some(persist), _) => persist.save_filter(&saved),
+            (true, _, Some(settings)) => settings.save_filter(&saved),
+            _ => Err(format!(
+                "Could not save filter {}, nowhere to save it",
+                name
+            )),
+        };
+        if result.log_err().is_none() {
+            return;
+        }
+
+        self.remove_saved_filter(name, is_global);
+        // Directory filters are listed first, each group by name.
+        let index = self
+            .saved_filters
+            .iter()
+            .position(|(other, _)| {
+                (other.is_global, other.name.to_lowercase()) > (is_global, name.to_lowercase())
+            })
+            .unwrap_or(self.saved_filters.len());
+        self.saved_filters.insert(index, (saved, None));
+        self.count_saved_filters();
+    }
+
+    fn delete_saved_filter(
+        &mut self,
+        name: &str,
+        is_global: bool,
+        settings: Option<&SettingsManager>,
+    ) {
+        let result = match (is_global, &self.persist, settings) {
+            (false, Some(persist), _) => persist.delete_saved_filter(name),
+            (true, _, Some(settings)) => settings.delete_saved_filter(name),
+            _ => Err(format!(
+                "Could not delete filter {}, nowhere it's saved",
+                name
+            )),
+        };
+        if result.log_err().is_some() {
+            self.remove_saved_filter(name, is_global);
+        }
+    }
+
+    fn remove_saved_filter(&mut self, name: &str, is_global: bool) {
+        self.saved_filters
+            .retain(|(saved, _)| saved.name != name || saved.is_global != is_global);
+    }
+
+    /// Applies a previously saved sort and filter, keeping the current file where possible.
+    fn restore(&mut self, sort: FileSort, filter: Filter) {
+        self.apply_filter(filter);
+        self.sort_by(sort);
+    }
+
+    pub fn update(
+        &mut self,
+        events: &mut EventSystem,
+        view: &ViewSettings,
+        settings: Option<&SettingsManager>,
+    ) {
         use crate::systems::events::*;
 
-        let new_events = events
+        let previous = self.current().map(|f| f.path.clone());
+        let mut new_events: Vec<_> = events
             .events()
             .filter_map(|event| match event {
                 AppEvent::Nav(nav) => match nav {
-                    Nav::ImagePrev => self.prev(),
-                    Nav::ImageNext => self.next(),
+                    Nav::ImagePrev => self.prev(view),
+                    Nav::ImageNext => self.next(view),
                     Nav::ImageIndex(idx) => self.set_current(*idx),
                 }
                 .map(|file| AppEvent::Load(file.clone())),
@@ -320,38 +694,104 @@ impl FileList {
                         self.apply_filter(new);
                         None
                     }
+                    Filter::NameOptions(options) => {
+                        let new = self.filter.clone().with_name_options(*options);
+                        self.apply_filter(new);
+                        None
+                    }
                     Filter::Rating(rating) => {
-                        let new = self.filter.clone().with_rating(&rating);
+                        let new = self.filter.clone().with_rating_filter(rating.clone());
                         self.apply_filter(new);
                         None
                     }
+                    Filter::Apply(filter) => {
+                        self.apply_filter(filter.clone());
+                        None
+                    }
+                    Filter::Save { name, is_global } => {
+                        self.save_filter(name, *is_global, settings);
+                        None
+                    }
+                    Filter::Delete { name, is_global } => {
+                        self.delete_saved_filter(name, *is_global, settings);
+                        None
+                    }
                 },
                 AppEvent::SetMeta(meta) => match meta {
-                    SetMeta::Rating(rating) => self.set_rating(rating.clone()).none(),
+                    SetMeta::Rating(rating) => {
+                        self.set_rating(rating.clone());
+                        None
+                    }
+                    SetMeta::View(path, view) => self.set_view(path, *view).none(),
                 },
-                AppEvent::Slideshow(slideshow) => match slideshow {
-                    Slideshow::Start(duration) => self.slideshow_start(*duration),
-                    Slideshow::Stop => self.slideshow_stop(),
+                AppEvent::Window(Window::Overlay(true)) => {
+                    self.show_saved_filters();
+                    None
+                }
+                AppEvent::ImageInfoRead(infos) => {
+                    self.set_image_info(infos);
+                    None
                 }
-                .none(),
                 _ => None,
             })
             .collect();
 
-        events.push_all(new_events);
+        // Filtering can change the current file too, including back from there being none.
+        let is_current_changed = self.current().map(|f| &f.path) != previous.as_ref();
+        let is_load_pushed = new_events
+            .iter()
+            .any(|event| matches!(event, AppEvent::Load(_)));
+        if is_current_changed && !is_load_pushed {
+            if let Some(file) = self.current() {
+                new_events.push(AppEvent::Load(file.clone()));
+            }
+        }
 
-        if let Some(dur) = self.slideshow {
-            let now = Instant::now();
+        if view.layout == Layout::Continuous {
+            // Pages are sized from their image info until they're decoded.
+            self.request_image_info();
 
-            if dur <= now.duration_since(self.slideshow_last_update) {
-                events.push(Nav::ImageNext.into());
-                self.slideshow_last_update = now;
+            // The continuous layout loads its own pages, so the file is loaded after leaving it.
+            let count = new_events.len();
+            new_events.retain(|event| !matches!(event, AppEvent::Load(_)));
+            self.is_current_loaded &= new_events.len() == count;
+        } else if !self.is_current_loaded {
+            self.is_current_loaded = true;
+            if let Some(file) = self.current() {
+                new_events.push(AppEvent::Load(file.clone()));
             }
         }
+
+        if !self.image_info_requests.is_empty() {
+            let paths = std::mem::take(&mut self.image_info_requests);
+            new_events.push(AppEvent::LoadImageInfo(paths));
+        }
+
+        let is_loading = new_events
+            .iter()
+            .any(|event| matches!(event, AppEvent::Load(_)));
+        events.push_all(new_events);
+
+        let is_layout_changed = events.events().any(|event| {
+            matches!(
+                event,
+                AppEvent::View(View::Layout(_)) | AppEvent::View(View::SingleFirstPage(_))
+            )
+        });
+        if is_layout_changed || (is_loading && view.layout == Layout::Spread) {
+            events.push(AppEvent::LoadCompanion(self.companion(view).cloned()));
+        }
+
+        self.is_state_changed |= events.events().any(|event| {
+            matches!(
+                event,
+                AppEvent::Nav(_) | AppEvent::Sort(_) | AppEvent::Filter(_)
+            )
+        });
     }
 }
 
-pub static SUPPORTED_FILE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "bmp", "gif"];
+pub static SUPPORTED_FILE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "bmp", "gif", "webp"];
 
 fn is_image_file(path: &Path) -> bool {
     if !path.is_file() {
@@ -366,7 +806,7 @@ fn is_image_file(path: &Path) -> bool {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::path::PathBuf;
+    use crate::data::{NameMode, NameOptions};
 
     #[test]
     pub fn filter_syncs_selected_item() {
@@ -382,6 +822,9 @@ mod tests {
         .map(|f| File {
             path: PathBuf::from(*f),
             rating: None,
+            view: None,
+            info: None,
+            metadata: None,
         })
         .collect();
         let mut list = FileList::from_files(files, None);
@@ -416,4 +859,229 @@ mod tests {
             "filter by '' after 'ac'"
         );
     }
+
+    #[test]
+    pub fn filter_matching_nothing() {
+        use crate::systems::events::{AppEvent, Filter as FilterEvent, Nav};
+
+        let files = vec![File::from("a.png"), File::from("b.png")];
+        let mut list = FileList::from_files(files, None);
+        list.set_current(1);
+
+        list.apply_filter(Filter::default().with_name("zzz"));
+        assert_eq!(list.len(), 0);
+        assert_eq!(list.current_index(), 0);
+        assert!(list.current().is_none());
+        assert!(list.next(&ViewSettings::default()).is_none());
+        assert!(list.prev(&ViewSettings::default()).is_none());
+
+        let mut events = EventSystem::new();
+        events.push(AppEvent::Nav(Nav::ImageNext));
+        events.update();
+        list.update(&mut events, &ViewSettings::default(), None);
+        events.update();
+        assert!(events.events().next().is_none(), "nothing to load");
+
+        events.push(AppEvent::Filter(FilterEvent::Text("b".to_owned())));
+        events.update();
+        list.update(&mut events, &ViewSettings::default(), None);
+        events.update();
+        let loaded: Vec<_> = events
+            .events()
+            .filter_map(|event| match event {
+                AppEvent::Load(file) => Some(file.name()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(loaded, vec!["b.png".to_owned()]);
+    }
+
+    #[test]
+    pub fn fuzzy_filter_ranks_best_matches_first() {
+        let files = vec![
+            "b_e_a_c_h.png",
+            "sunset.png",
+            "2019_beach_trip.png",
+            "beach.png",
+            "abacus.png",
+        ]
+        .into_iter()
+        .map(File::from)
+        .collect();
+        let mut list = FileList::from_files(files, None);
+        let fuzzy = NameOptions {
+            mode: NameMode::Fuzzy,
+            ..NameOptions::default()
+        };
+
+        list.apply_filter(Filter::default().with_name_options(fuzzy).with_name("bch"));
+        let names: Vec<_> = (0..list.len())
+            .filter_map(|i| list.get_file(i).map(File::name))
+            .collect();
+        assert_eq!(
+            names,
+            vec!["b_e_a_c_h.png", "beach.png", "2019_beach_trip.png"]
+        );
+
+        list.apply_filter(Filter::default().with_name_options(fuzzy).with_name("beac"));
+        assert_eq!(
+            list.get_file(0).map(File::name),
+            Some("beach.png".to_owned())
+        );
+        assert_eq!(list.len(), 3);
+        assert_eq!(list.total_len(), 5);
+    }
+
+    #[test]
+    pub fn spread_navigation_moves_by_pages() {
+        // The files don't exist, so none of them are wide and every pair of pages is a spread.
+        let files = (1..=6)
+            .map(|i| File::from(&*format!("{}.png", i)))
+            .collect();
+        let mut list = FileList::from_files(files, None);
+        let view = ViewSettings {
+            layout: Layout::Spread,
+            single_first_page: true,
+            ..ViewSettings::default()
+        };
+
+        let index = |list: &FileList| list.current_index();
+        assert!(list.companion(&view).is_none(), "first page is alone");
+        list.next(&view);
+        assert_eq!(index(&list), 1);
+        assert_eq!(
+            list.companion(&view).map(File::name),
+            Some("3.png".to_owned())
+        );
+        list.next(&view);
+        assert_eq!(index(&list), 3);
+        list.prev(&view);
+        assert_eq!(index(&list), 1);
+        list.prev(&view);
+        assert_eq!(index(&list), 0);
+
+        let view = ViewSettings {
+            single_first_page: false,
+            ..view
+        };
+        list.next(&view);
+        assert_eq!(index(&list), 2);
+        list.prev(&view);
+        assert_eq!(index(&list), 0);
+
+        // A wide page is shown by itself.
+        list.files[3].info = Some(Ok(ImageInfo {
+            format: crate::data::ImageFormat::Png,
+            width: 200,
+            height: 100,
+            frame_count: 1,
+            has_alpha: false,
+        }));
+        list.next(&view);
+        assert!(list.companion(&view).is_none());
+        list.next(&view);
+        assert_eq!(index(&list), 3);
+        list.next(&view);
+        assert_eq!(index(&list), 4);
+    }
+
+    #[test]
+    pub fn image_info_is_read_in_the_background() {
+        use crate::systems::events::AppEvent;
+
+        let files = vec![File::from("tall.png"), File::from("wide.png")];
+        let mut list = FileList::from_files(files, None);
+        list.apply_filter(Filter::default().with_name("is:landscape"));
+        assert_eq!(list.len(), 0, "unknown until read");
+
+        let mut events = EventSystem::new();
+        list.update(&mut events, &ViewSettings::default(), None);
+        events.update();
+        let requested: Vec<_> = events
+            .events()
+            .filter_map(|event| match event {
+                AppEvent::LoadImageInfo(paths) => Some(paths.clone()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            requested,
+            vec![vec![PathBuf::from("tall.png"), PathBuf::from("wide.png")]]
+        );
+
+        let info = |width, height| ImageInfo {
+            format: crate::data::ImageFormat::Png,
+            width,
+            height,
+            frame_count: 1,
+            has_alpha: false,
+        };
+        events.push(AppEvent::ImageInfoRead(vec![
+            (PathBuf::from("tall.png"), Ok(info(100, 200))),
+            (PathBuf::from("wide.png"), Ok(info(200, 100))),
+        ]));
+        events.update();
+        list.update(&mut events, &ViewSettings::default(), None);
+        assert_eq!(list.current().map(File::name), Some("wide.png".to_owned()));
+        assert_eq!(list.len(), 1);
+    }
+
+    #[test]
+    pub fn saved_filters_count_files_as_they_are_rated() {
+        use crate::data::RatingFilter;
+        use crate::systems::events::{AppEvent, SetMeta, Window};
+
+        let files = vec![File::from("1.png"), File::from("2.png")];
+        let mut list = FileList::from_files(files, None);
+        let saved = |name: &str, rating| SavedFilter {
+            name: name.to_owned(),
+            filter: Filter::default().with_rating_filter(Some(rating)),
+            is_global: false,
+        };
+        list.add_saved_filters(vec![
+            saved("Favourites", RatingFilter::AtLeast(Rating::from(4))),
+            saved("To Rate", RatingFilter::Unrated),
+        ]);
+        let counts = |list: &FileList| -> Vec<Option<usize>> {
+            list.saved_filters()
+                .iter()
+                .map(|(_, count)| *count)
+                .collect()
+        };
+        assert_eq!(counts(&list), vec![Some(0), Some(2)]);
+
+        let mut events = EventSystem::new();
+        events.push(AppEvent::SetMeta(SetMeta::Rating(Some(Rating::from(5)))));
+        events.update();
+        list.update(&mut events, &ViewSettings::default(), None);
+        assert_eq!(counts(&list), vec![Some(1), Some(1)]);
+
+        // Counting by image info reads every file, so it waits until the counts are shown.
+        list.add_saved_filters(vec![SavedFilter {
+            name: "Wide".to_owned(),
+            filter: Filter::default().with_name("is:landscape"),
+            is_global: true,
+        }]);
+        assert_eq!(counts(&list), vec![Some(1), Some(1), None]);
+        events.push(AppEvent::Window(Window::Overlay(true)));
+        events.update();
+        list.update(&mut events, &ViewSettings::default(), None);
+        assert_eq!(counts(&list), vec![Some(1), Some(1), Some(0)]);
+
+        // Files are counted as their image info arrives.
+        let info = ImageInfo {
+            format: crate::data::ImageFormat::Png,
+            width: 200,
+            height: 100,
+            frame_count: 1,
+            has_alpha: false,
+        };
+        events.push(AppEvent::ImageInfoRead(vec![(
+            PathBuf::from("2.png"),
+            Ok(info),
+        )]));
+        events.update();
+        list.update(&mut events, &ViewSettings::default(), None);
+        assert_eq!(counts(&list), vec![Some(1), Some(1), Some(1)]);
+    }
 }
@@ -1,27 +1,145 @@
 use std::default::Default;
+use std::fmt;
+use std::str::FromStr;
 
-use super::{File, Rating};
+use super::{File, NameOptions, Query, Rating};
 
-#[derive(Debug, Clone)]
+/// Which ratings a filter keeps.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RatingFilter {
+    /// Files which haven't been rated yet.
+    Unrated,
+    /// Files with any rating.
+    Rated,
+    AtLeast(Rating),
+    Exactly(Rating),
+    /// Files rated from the first to the second rating, inclusive.
+    Between(Rating, Rating),
+}
+
+impl RatingFilter {
+    /// The lowest and highest rating kept, or `None` for unrated files.
+    pub fn bounds(&self) -> Option<(usize, usize)> {
+        match self {
+            RatingFilter::Unrated => None,
+            RatingFilter::Rated => Some((Rating::min_value(), Rating::max_value())),
+            RatingFilter::AtLeast(min) => Some((min.clone().into(), Rating::max_value())),
+            RatingFilter::Exactly(rating) => {
+                let rating = rating.clone().into();
+                Some((rating, rating))
+            }
+            RatingFilter::Between(min, max) => Some((min.clone().into(), max.clone().into())),
+        }
+    }
+
+    pub fn matches(&self, rating: &Option<Rating>) -> bool {
+        match (self.bounds(), rating) {
+            (None, rating) => rating.is_none(),
+            (Some(_), None) => false,
+            (Some((min, max)), Some(rating)) => (min..=max).contains(&rating.clone().into()),
+        }
+    }
+
+    /// Whether every rating this keeps is also kept by `other`.
+    pub fn is_subset_of(&self, other: &RatingFilter) -> bool {
+        match (self.bounds(), other.bounds()) {
+            (None, None) => true,
+            (Some((min, max)), Some((other_min, other_max))) => {
+                other_min <= min && max <= other_max
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Formats the filter for saving, such as `>=3`, `=3`, `2..4`, `unrated` or `rated`.
+impl fmt::Display for RatingFilter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RatingFilter::Unrated => f.write_str("unrated"),
+            RatingFilter::Rated => f.write_str("rated"),
+            RatingFilter::AtLeast(min) => write!(f, ">={}", min.as_i64()),
+            RatingFilter::Exactly(rating) => write!(f, "={}", rating.as_i64()),
+            RatingFilter::Between(min, max) => write!(f, "{}..{}", min.as_i64(), max.as_i64()),
+        }
+    }
+}
+
+impl FromStr for RatingFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rating = |r: &str| {
+            r.parse::<i64>()
+                .map(Rating::from)
+                .map_err(|_| format!("Invalid rating filter: {}", s))
+        };
+
+        match s {
+            "unrated" => Ok(RatingFilter::Unrated),
+            "rated" => Ok(RatingFilter::Rated),
+            _ if s.starts_with(">=") => rating(&s[2..]).map(RatingFilter::AtLeast),
+            _ if s.starts_with('=') => rating(&s[1..]).map(RatingFilter::Exactly),
+            _ => match s.split_once("..") {
+                Some((min, max)) => Ok(RatingFilter::Between(rating(min)?, rating(max)?)),
+                // Older versions saved only the minimum rating.
+                None => rating(s).map(RatingFilter::AtLeast),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Filter {
-    /// The file name should contain this. This value should always be lowercase.
-    file_name: Option<String>,
-    rating: Option<Rating>,
+    /// The query as it was typed into the filter box.
+    text: Option<String>,
+    name_options: NameOptions,
+    query: Option<Query>,
+    rating: Option<RatingFilter>,
 }
 
 // Builder methods
 impl Filter {
-    pub fn with_name(mut self, name: &str) -> Self {
-        self.file_name = if name.is_empty() {
-            None
-        } else {
-            Some(name.to_lowercase())
-        };
+    /// Filters by a query, or by file name if the query is invalid.
+    pub fn with_name(mut self, text: &str) -> Self {
+        self.text = Some(text.to_owned()).filter(|t| !t.trim().is_empty());
+        self.parse_query();
         self
     }
 
-    pub fn with_rating(mut self, rating: &Option<Rating>) -> Self {
-        self.rating = rating.clone();
+    /// Changes how names are matched, unless the text is invalid with the new options, which
+    /// leaves the filter as it was so its options always match the query it filters by.
+    pub fn with_name_options(mut self, options: NameOptions) -> Self {
+        if let Some(text) = &self.text {
+            match Query::parse(text, options) {
+                Ok(query) => self.query = Some(query),
+                Err(e) => {
+                    log::warn!("Keeping the previous name options, the query is invalid: {}", e);
+                    return self;
+                }
+            }
+        }
+        self.name_options = options;
+        self
+    }
+
+    fn parse_query(&mut self) {
+        let options = self.name_options;
+        self.query = self.text.as_ref().map(|text| {
+            Query::parse(text, options).unwrap_or_else(|e| {
+                log::warn!("Filtering by name, the query is invalid: {}", e);
+                Query::name(text)
+            })
+        });
+    }
+
+    /// Keeps files rated at least `rating`, as rating filters did before they had modes.
+    pub fn with_rating(self, rating: &Option<Rating>) -> Self {
+        self.with_rating_filter(rating.clone().map(RatingFilter::AtLeast))
+    }
+
+    pub fn with_rating_filter(mut self, rating: Option<RatingFilter>) -> Self {
+        self.rating = rating;
         self
     }
 }
@@ -29,55 +147,92 @@ impl Filter {
 impl Default for Filter {
     fn default() -> Self {
         Filter {
-            file_name: None,
+            text: None,
+            name_options: NameOptions::default(),
+            query: None,
             rating: None,
         }
     }
 }
 
+// Accessors
+impl Filter {
+    pub fn name(&self) -> Option<&str> {
+        self.text.as_deref()
+    }
+
+    pub fn name_options(&self) -> NameOptions {
+        self.name_options
+    }
+
+    pub fn rating(&self) -> Option<&RatingFilter> {
+        self.rating.as_ref()
+    }
+}
+
 // Utility
 impl Filter {
     pub fn is_subset_of(&self, other: &Filter) -> bool {
-        let is_file_name_subset = match (&self.file_name, &other.file_name) {
+        let is_query_subset = match (&self.query, &other.query) {
             (None, None) => true,
             (Some(_), None) => true,
             (None, Some(_)) => false,
-            (Some(new), Some(current)) => new.starts_with(current),
+            (Some(new), Some(current)) => new.implies(current),
         };
 
         let is_rating_subset = match (&self.rating, &other.rating) {
             (None, None) => true,
             (Some(_), None) => true,
             (None, Some(_)) => false,
-            (Some(new), Some(current)) => current <= new,
+            (Some(new), Some(current)) => new.is_subset_of(current),
         };
 
-        is_file_name_subset && is_rating_subset
+        is_query_subset && is_rating_subset
     }
 
     pub fn matches(&self, file: &File) -> bool {
-        let name_matches = match (&self.file_name, file.path.file_name()) {
-            (Some(_), None) => false,
-            (None, _) => true,
-            (Some(filter), Some(name)) => name
-                .to_str()
-                .map(|s| s.to_lowercase().contains(&*filter))
-                .unwrap_or(false),
-        };
+        let query_matches = self.query.as_ref().is_none_or(|q| q.matches(file));
 
-        let rating_matches = match (&self.rating, &file.rating) {
-            (Some(_), None) => false,
-            (None, _) => true,
-            (Some(filter), Some(rating)) => filter <= rating,
-        };
+        let rating_matches = self.rating.as_ref().is_none_or(|r| r.matches(&file.rating));
+
+        query_matches && rating_matches
+    }
+
+    /// Whether matching reads image headers, which the file list caches first.
+    pub fn needs_image_info(&self) -> bool {
+        self.query.as_ref().is_some_and(Query::needs_image_info)
+    }
+
+    /// Whether files should be ranked by `score` rather than only sorted.
+    pub fn is_ranked(&self) -> bool {
+        self.query.as_ref().is_some_and(Query::is_ranked)
+    }
+
+    /// How well a file matches a fuzzy filter, higher is better.
+    pub fn score(&self, file: &File) -> i64 {
+        self.query.as_ref().map_or(0, |q| q.score(&file.name()))
+    }
 
-        name_matches && rating_matches
+    /// The index of each char in the name matched by a fuzzy filter, for highlighting.
+    pub fn highlights(&self, name: &str) -> Vec<usize> {
+        self.query
+            .as_ref()
+            .map_or_else(Vec::new, |q| q.highlights(name))
     }
 }
 
+/// A filter the user named so it can be applied again.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SavedFilter {
+    pub name: String,
+    pub filter: Filter,
+    /// Saved in the user's settings for every directory, rather than only this one.
+    pub is_global: bool,
+}
+
 #[cfg(test)]
 mod tests {
-    use super::super::{File, Rating};
+    use super::super::{File, NameMode, Rating};
     use super::*;
     use std::path::PathBuf;
 
@@ -153,6 +308,9 @@ mod tests {
         let file = File {
             path: PathBuf::from(r"C:\path\to\file.png"),
             rating: None,
+            view: None,
+            info: None,
+            metadata: None,
         };
 
         test_matches(&Filter::default(), &file, true);
@@ -170,11 +328,113 @@ mod tests {
         test_matches(&Filter::default().with_name("other.png"), &file, false);
     }
 
+    #[test]
+    pub fn matches_by_normalized_name() {
+        // Names from macOS are often decomposed, with the accent as a separate char.
+        let composed = File::from("caf\u{e9}.png");
+        let decomposed = File::from("cafe\u{301}.png");
+        let full_width = File::from("ＩＭＧ＿００１.png");
+        let folding = NameOptions {
+            is_folding: true,
+            ..NameOptions::default()
+        };
+        let with =
+            |options, name: &str| Filter::default().with_name_options(options).with_name(name);
+
+        for file in &[&composed, &decomposed] {
+            test_matches(&with(NameOptions::default(), "caf\u{e9}"), file, true);
+            test_matches(&with(NameOptions::default(), "cafe\u{301}"), file, true);
+            test_matches(&with(NameOptions::default(), "CAF\u{c9}"), file, true);
+            test_matches(&with(NameOptions::default(), "cafe"), file, false);
+            test_matches(&with(folding, "cafe"), file, true);
+            test_matches(&with(folding, "CAF\u{c9}"), file, true);
+        }
+
+        test_matches(&with(NameOptions::default(), "img_001"), &full_width, false);
+        test_matches(&with(folding, "img_001"), &full_width, true);
+        test_matches(&with(folding, "ＩＭＧ"), &File::from("img.png"), true);
+
+        let glob = NameOptions {
+            mode: NameMode::Glob,
+            ..folding
+        };
+        test_matches(&with(glob, "caf?.png"), &decomposed, true);
+        let fuzzy = NameOptions {
+            mode: NameMode::Fuzzy,
+            ..NameOptions::default()
+        };
+        test_matches(&with(fuzzy, "c\u{e9}p"), &decomposed, true);
+        assert_eq!(
+            with(fuzzy, "c\u{e9}p").highlights(&decomposed.name()),
+            vec![0, 3, 4, 6]
+        );
+        // Chars which lowercase to several chars are cased the same way in patterns and names.
+        let dotted = File::from("\u{130}stanbul.png");
+        test_matches(&with(fuzzy, "\u{130}st"), &dotted, true);
+        assert_eq!(
+            with(fuzzy, "\u{130}st").highlights(&dotted.name()),
+            vec![0, 1, 2]
+        );
+    }
+
+    #[test]
+    pub fn matches_by_glob_and_regex() {
+        let file = File {
+            path: PathBuf::from("File.png"),
+            rating: None,
+            view: None,
+            info: None,
+            metadata: None,
+        };
+        let with = |mode, is_case_sensitive, name: &str| {
+            Filter::default()
+                .with_name_options(NameOptions {
+                    mode,
+                    is_case_sensitive,
+                    ..NameOptions::default()
+                })
+                .with_name(name)
+        };
+
+        test_matches(&with(NameMode::Glob, false, "file*png"), &file, true);
+        test_matches(&with(NameMode::Glob, false, "?ile.[pj]ng"), &file, true);
+        test_matches(&with(NameMode::Glob, false, "file[!.]png"), &file, false);
+        test_matches(&with(NameMode::Glob, false, "file"), &file, false);
+        test_matches(&with(NameMode::Glob, true, "file*"), &file, false);
+        test_matches(&with(NameMode::Glob, true, "File*"), &file, true);
+        test_matches(&with(NameMode::Regex, false, r"^f\w+\.PNG$"), &file, true);
+        test_matches(&with(NameMode::Regex, true, r"^f\w+\.PNG$"), &file, false);
+        test_matches(&with(NameMode::Regex, false, "le.p"), &file, true);
+        test_matches(&with(NameMode::Substring, true, "file"), &file, false);
+        test_matches(&with(NameMode::Substring, true, "File"), &file, true);
+
+        // `[` isn't a valid regular expression, so switching to regex keeps the filter as it was.
+        let options = |mode| NameOptions {
+            mode,
+            is_case_sensitive: true,
+            ..NameOptions::default()
+        };
+        let bracket = File {
+            path: PathBuf::from("file[1].png"),
+            ..file
+        };
+        let filter = Filter::default()
+            .with_name_options(options(NameMode::Substring))
+            .with_name("File[")
+            .with_name_options(options(NameMode::Regex));
+        assert_eq!(filter.name_options(), options(NameMode::Substring));
+        test_matches(&filter, &bracket, false);
+        test_matches(&filter.with_name("file["), &bracket, true);
+    }
+
     #[test]
     pub fn matches_by_rating() {
         let file = File {
             path: PathBuf::from(""),
             rating: Some(Rating::from(3)),
+            view: None,
+            info: None,
+            metadata: None,
         };
 
         test_matches(&Filter::default(), &file, true);
@@ -205,6 +465,69 @@ mod tests {
         );
     }
 
+    #[test]
+    pub fn matches_by_rating_filter() {
+        let unrated = File::from("unrated.png");
+        let rated = |r: i64| File {
+            rating: Some(Rating::from(r)),
+            ..File::from("rated.png")
+        };
+        let with = |rating| Filter::default().with_rating_filter(Some(rating));
+
+        test_matches(&with(RatingFilter::Unrated), &unrated, true);
+        test_matches(&with(RatingFilter::Unrated), &rated(1), false);
+        test_matches(&with(RatingFilter::Rated), &unrated, false);
+        test_matches(&with(RatingFilter::Rated), &rated(1), true);
+        test_matches(
+            &with(RatingFilter::Exactly(Rating::from(3))),
+            &rated(3),
+            true,
+        );
+        test_matches(
+            &with(RatingFilter::Exactly(Rating::from(3))),
+            &rated(4),
+            false,
+        );
+        let between = || RatingFilter::Between(Rating::from(2), Rating::from(4));
+        test_matches(&with(between()), &rated(1), false);
+        test_matches(&with(between()), &rated(2), true);
+        test_matches(&with(between()), &rated(4), true);
+        test_matches(&with(between()), &rated(5), false);
+        test_matches(&with(between()), &unrated, false);
+
+        test_is_subset_of(
+            &with(RatingFilter::Unrated),
+            &with(RatingFilter::Rated),
+            false,
+        );
+        test_is_subset_of(&with(between()), &with(RatingFilter::Rated), true);
+        test_is_subset_of(
+            &with(RatingFilter::Exactly(Rating::from(3))),
+            &with(between()),
+            true,
+        );
+        test_is_subset_of(
+            &with(RatingFilter::AtLeast(Rating::from(3))),
+            &with(between()),
+            false,
+        );
+    }
+
+    #[test]
+    pub fn rating_filter_round_trips_through_strings() {
+        for filter in [
+            RatingFilter::Unrated,
+            RatingFilter::Rated,
+            RatingFilter::AtLeast(Rating::from(2)),
+            RatingFilter::Exactly(Rating::from(5)),
+            RatingFilter::Between(Rating::from(1), Rating::from(3)),
+        ] {
+            assert_eq!(filter.to_string().parse(), Ok(filter));
+        }
+        assert_eq!("4".parse(), Ok(RatingFilter::AtLeast(Rating::from(4))));
+        assert!("4..".parse::<RatingFilter>().is_err());
+    }
+
     fn test_matches(filter: &Filter, file: &File, expected: bool) {
         assert_eq!(
             filter.matches(file),
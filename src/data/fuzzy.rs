@@ -0,0 +1,161 @@
+/// Where a pattern was found in a name by `fuzzy_match` and how good the match is.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FuzzyMatch {
+    /// Higher scores are better matches.
+    pub score: i64,
+    /// The index of each matched char in the name.
+    pub positions: Vec<usize>,
+}
+
+const SCORE_MATCH: i64 = 16;
+const BONUS_CONSECUTIVE: i64 = 24;
+const BONUS_WORD_START: i64 = 24;
+const BONUS_NUMBER_START: i64 = 8;
+const PENALTY_GAP_START: i64 = 6;
+const PENALTY_GAP: i64 = 2;
+const PENALTY_LEADING: i64 = 1;
+const MAX_PENALTY_LEADING: i64 = 12;
+
+/// Whether the chars of `pattern` appear in `name` in order, which is all a fuzzy match needs.
+pub fn is_subsequence(pattern: &[char], name: impl Iterator<Item = char>) -> bool {
+    let mut pattern = pattern.iter().peekable();
+    for c in name {
+        if pattern.peek() == Some(&&c) {
+            pattern.next();
+        }
+    }
+    pattern.peek().is_none()
+}
+
+/// Lowercases a char unless matching is case sensitive.
+///
+/// Only the first char of its lowercase mapping is kept, so positions in a name still line up
+/// with its chars. Patterns are lowercased the same way so they match.
+pub fn fold_case(c: char, is_case_sensitive: bool) -> char {
+    if is_case_sensitive {
+        c
+    } else {
+        c.to_lowercase().next().unwrap_or(c)
+    }
+}
+
+/// Finds the best placement of the chars of `pattern` in order in `name`, like a command palette.
+///
+/// Matches at the start of words and runs of consecutive chars score higher, gaps score lower.
+pub fn fuzzy_match(pattern: &[char], name: &[char], is_case_sensitive: bool) -> Option<FuzzyMatch> {
+    let chars: Vec<char> = name
+        .iter()
+        .map(|&c| fold_case(c, is_case_sensitive))
+        .collect();
+    let first = *pattern.first()?;
+
+    (0..chars.len())
+        .filter(|&start| chars[start] == first)
+        .filter_map(|start| match_from(pattern, &chars, start))
+        .map(|positions| FuzzyMatch {
+            score: score(&positions, name),
+            positions,
+        })
+        .max_by_key(|m| m.score)
+}
+
+/// Matches the pattern greedily from `start`, then tightens it from the end to avoid gaps.
+fn match_from(pattern: &[char], chars: &[char], start: usize) -> Option<Vec<usize>> {
+    let mut end = start;
+    let mut remaining = pattern.iter();
+    let mut next = remaining.next();
+    for (i, c) in chars.iter().enumerate().skip(start) {
+        if next == Some(c) {
+            end = i;
+            next = remaining.next();
+            if next.is_none() {
+                break;
+            }
+        }
+    }
+    if next.is_some() {
+        return None;
+    }
+
+    let mut positions = Vec::with_capacity(pattern.len());
+    let mut remaining = pattern.iter().rev();
+    let mut next = remaining.next();
+    for i in (start..=end).rev() {
+        if next == Some(&chars[i]) {
+            positions.push(i);
+            next = remaining.next();
+        }
+    }
+    positions.reverse();
+    Some(positions)
+}
+
+fn score(positions: &[usize], chars: &[char]) -> i64 {
+    let mut score = 0;
+    let mut prev: Option<usize> = None;
+    for &i in positions {
+        score += SCORE_MATCH + boundary_bonus(chars, i);
+        score += match prev {
+            Some(prev) if prev + 1 == i => BONUS_CONSECUTIVE,
+            Some(prev) => -PENALTY_GAP_START - PENALTY_GAP * (i - prev - 2) as i64,
+            None => -(PENALTY_LEADING * i as i64).min(MAX_PENALTY_LEADING),
+        };
+        prev = Some(i);
+    }
+    score
+}
+
+/// The bonus for matching a char which starts a word, such as after `_` or in `camelCase`.
+fn boundary_bonus(chars: &[char], i: usize) -> i64 {
+    let c = chars[i];
+    let prev = match i.checked_sub(1) {
+        Some(prev) => chars[prev],
+        None => return BONUS_WORD_START,
+    };
+
+    let is_word_start = !prev.is_alphanumeric() && c.is_alphanumeric();
+    let is_camel_case = prev.is_lowercase() && c.is_uppercase();
+    if is_word_start || is_camel_case {
+        BONUS_WORD_START
+    } else if !prev.is_numeric() && c.is_numeric() {
+        BONUS_NUMBER_START
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chars(text: &str) -> Vec<char> {
+        text.chars().collect()
+    }
+
+    #[test]
+    pub fn fuzzy_match_prefers_word_starts_and_runs() {
+        let found =
+            fuzzy_match(&chars("dsc12"), &chars("IMG_20190101_DSC_0012.jpg"), false).unwrap();
+        assert_eq!(found.positions, vec![13, 14, 15, 19, 20]);
+
+        let found = fuzzy_match(&chars("hp"), &chars("holiday_photo.png"), false).unwrap();
+        assert_eq!(found.positions, vec![0, 8]);
+
+        assert!(fuzzy_match(&chars("xyz"), &chars("holiday_photo.png"), false).is_none());
+        assert!(fuzzy_match(&chars("hp"), &chars("HOLIDAY_PHOTO.JPG"), true).is_none());
+        assert!(fuzzy_match(&[], &chars("holiday_photo.png"), false).is_none());
+    }
+
+    #[test]
+    pub fn fuzzy_match_ranks_closer_matches_higher() {
+        let score = |name| fuzzy_match(&chars("beach"), &chars(name), false).map(|m| m.score);
+        let exact = score("beach.png");
+        let word = score("2019_beach_trip.png");
+        let spread = score("bxexaxcxh.png");
+        let scattered = score("big red apple crate hat.png");
+
+        assert!(exact > word, "{:?} > {:?}", exact, word);
+        assert!(word > spread, "{:?} > {:?}", word, spread);
+        assert!(word > scattered, "{:?} > {:?}", word, scattered);
+    }
+}
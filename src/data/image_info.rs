@@ -0,0 +1,301 @@
+use std::cmp::Ordering;
+use std::convert::AsRef;
+use std::fmt;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::support::ErrToString;
+
+/// An image format, recognised from the start of the file rather than its extension.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ImageFormat {
+    Png,
+    Jpeg,
+    Gif,
+    Bmp,
+    WebP,
+}
+
+pub static IMAGE_FORMATS: &[ImageFormat] = &[
+    ImageFormat::Png,
+    ImageFormat::Jpeg,
+    ImageFormat::Gif,
+    ImageFormat::Bmp,
+    ImageFormat::WebP,
+];
+
+impl fmt::Display for ImageFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_ref())
+    }
+}
+
+impl AsRef<str> for ImageFormat {
+    fn as_ref(&self) -> &str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Jpeg => "jpeg",
+            ImageFormat::Gif => "gif",
+            ImageFormat::Bmp => "bmp",
+            ImageFormat::WebP => "webp",
+        }
+    }
+}
+
+impl ImageFormat {
+    /// Recognises a format from the magic bytes at the start of a file.
+    fn detect(header: &[u8]) -> Option<ImageFormat> {
+        if header.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(ImageFormat::Png)
+        } else if header.starts_with(&[0xff, 0xd8, 0xff]) {
+            Some(ImageFormat::Jpeg)
+        } else if header.starts_with(b"GIF8") {
+            Some(ImageFormat::Gif)
+        } else if header.starts_with(b"BM") {
+            Some(ImageFormat::Bmp)
+        } else if header.starts_with(b"RIFF") && header.get(8..12) == Some(b"WEBP") {
+            Some(ImageFormat::WebP)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Orientation {
+    Landscape,
+    Portrait,
+    Square,
+}
+
+/// What an image's headers say about it, cached so filters don't need to open every file.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ImageInfo {
+    pub format: ImageFormat,
+    pub width: u32,
+    pub height: u32,
+    /// Always 1 for PNGs, animated PNGs only show their first frame. GIFs are only read up to
+    /// their second frame.
+    pub frame_count: u32,
+    pub has_alpha: bool,
+}
+
+impl ImageInfo {
+    /// Reads the info from the image's headers without decoding it.
+    pub fn read(path: &Path) -> Result<ImageInfo, String> {
+        let mut reader = BufReader::new(std::fs::File::open(path).err_to_string()?);
+        let mut header = Vec::with_capacity(12);
+        reader
+            .by_ref()
+            .take(12)
+            .read_to_end(&mut header)
+            .err_to_string()?;
+        let format = ImageFormat::detect(&header)
+            .ok_or_else(|| format!("Unsupported image type: {}", path.display()))?;
+        reader.seek(SeekFrom::Start(0)).err_to_string()?;
+
+        match format {
+            ImageFormat::Png => read_still(format, image::png::PNGDecoder::new(reader)),
+            ImageFormat::Jpeg => read_still(format, image::jpeg::JPEGDecoder::new(reader)),
+            ImageFormat::Bmp => read_still(format, image::bmp::BMPDecoder::new(reader)),
+            ImageFormat::Gif => read_gif(reader),
+            ImageFormat::WebP => read_webp(reader),
+        }
+    }
+
+    pub fn orientation(&self) -> Orientation {
+        match self.width.cmp(&self.height) {
+            Ordering::Greater => Orientation::Landscape,
+            Ordering::Less => Orientation::Portrait,
+            Ordering::Equal => Orientation::Square,
+        }
+    }
+
+    pub fn is_animated(&self) -> bool {
+        self.frame_count > 1
+    }
+
+    /// The length of the shorter side, which is what resolutions like 1080p and 4K describe.
+    pub fn resolution(&self) -> u32 {
+        self.width.min(self.height)
+    }
+}
+
+fn read_still(
+    format: ImageFormat,
+    mut decoder: impl image::ImageDecoder,
+) -> Result<ImageInfo, String> {
+    use image::ColorType;
+
+    let (width, height) = decoder.dimensions().err_to_string()?;
+    let has_alpha = matches!(
+        decoder.colortype().err_to_string()?,
+        ColorType::GrayA(_) | ColorType::RGBA(_) | ColorType::BGRA(_)
+    );
+    Ok(ImageInfo {
+        format,
+        width,
+        height,
+        frame_count: 1,
+        has_alpha,
+    })
+}
+
+/// Reads a GIF's first two frames, which is enough to tell whether it's animated.
+fn read_gif(reader: impl Read) -> Result<ImageInfo, String> {
+    let mut reader = gif::Decoder::new(reader).read_info().err_to_string()?;
+    let width = u32::from(reader.width());
+    let height = u32::from(reader.height());
+
+    let mut frame_count = 0;
+    let mut has_alpha = false;
+    while frame_count < 2 {
+        match reader.next_frame_info().err_to_string()? {
+            Some(frame) => has_alpha |= frame.transparent.is_some(),
+            None => break,
+        }
+        frame_count += 1;
+    }
+
+    Ok(ImageInfo {
+        format: ImageFormat::Gif,
+        width,
+        height,
+        frame_count,
+        has_alpha,
+    })
+}
+
+/// Reads the chunks of a WebP file, as the `image` crate only reads lossy WebP headers.
+fn read_webp(mut reader: impl Read + Seek) -> Result<ImageInfo, String> {
+    let mut size = None;
+    let mut is_extended = false;
+    let mut has_alpha = false;
+    let mut frame_count = 0;
+
+    // Chunks follow the 12 byte RIFF header, each padded to an even length.
+    let mut position = 12;
+    loop {
+        reader.seek(SeekFrom::Start(position)).err_to_string()?;
+        let mut header = [0; 8];
+        if reader.read_exact(&mut header).is_err() {
+            break;
+        }
+        let length = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+        let mut data = Vec::with_capacity(10);
+        reader
+            .by_ref()
+            .take(10)
+            .read_to_end(&mut data)
+            .err_to_string()?;
+
+        match (&header[..4], data.as_slice()) {
+            (b"VP8X", &[flags, _, _, _, w0, w1, w2, h0, h1, h2]) => {
+                is_extended = true;
+                has_alpha = flags & 0x10 != 0;
+                size = Some((
+                    1 + u32::from_le_bytes([w0, w1, w2, 0]),
+                    1 + u32::from_le_bytes([h0, h1, h2, 0]),
+                ));
+            }
+            (b"VP8 ", &[_, _, _, _, _, _, w0, w1, h0, h1]) if size.is_none() => {
+                size = Some((
+                    u32::from(u16::from_le_bytes([w0, w1]) & 0x3fff),
+                    u32::from(u16::from_le_bytes([h0, h1]) & 0x3fff),
+                ));
+            }
+            (b"VP8L", &[0x2f, b0, b1, b2, b3, ..]) if size.is_none() => {
+                let bits = u32::from_le_bytes([b0, b1, b2, b3]);
+                size = Some((1 + (bits & 0x3fff), 1 + ((bits >> 14) & 0x3fff)));
+                // Only a hint, the extended header is used when there is one.
+                has_alpha |= !is_extended && bits & (1 << 28) != 0;
+            }
+            (b"ANMF", _) => frame_count += 1,
+            _ => (),
+        }
+
+        position += 8 + u64::from(length) + u64::from(length & 1);
+    }
+
+    let (width, height) = size.ok_or("No image data found in WebP file")?;
+    Ok(ImageInfo {
+        format: ImageFormat::WebP,
+        width,
+        height,
+        frame_count: frame_count.max(1),
+        has_alpha,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn webp(chunks: &[(&[u8], &[u8])]) -> Vec<u8> {
+        let mut bytes = b"RIFF\0\0\0\0WEBP".to_vec();
+        for (name, data) in chunks {
+            bytes.extend_from_slice(name);
+            bytes.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(data);
+            if data.len() % 2 == 1 {
+                bytes.push(0);
+            }
+        }
+        bytes
+    }
+
+    #[test]
+    pub fn detect_format_from_header() {
+        assert_eq!(
+            ImageFormat::detect(b"\x89PNG\r\n\x1a\n\0\0\0\x0d"),
+            Some(ImageFormat::Png)
+        );
+        assert_eq!(
+            ImageFormat::detect(&[0xff, 0xd8, 0xff, 0xe0]),
+            Some(ImageFormat::Jpeg)
+        );
+        assert_eq!(ImageFormat::detect(b"GIF89a"), Some(ImageFormat::Gif));
+        assert_eq!(ImageFormat::detect(b"BM\0\0"), Some(ImageFormat::Bmp));
+        assert_eq!(
+            ImageFormat::detect(b"RIFF\0\0\0\0WEBP"),
+            Some(ImageFormat::WebP)
+        );
+        assert_eq!(ImageFormat::detect(b"RIFF\0\0\0\0WAVE"), None);
+        assert_eq!(ImageFormat::detect(b"text"), None);
+    }
+
+    #[test]
+    pub fn read_webp_headers() {
+        // An animated 3840x2160 canvas with alpha and three frames.
+        let extended = webp(&[
+            (b"VP8X", &[0x12, 0, 0, 0, 0xff, 0x0e, 0, 0x6f, 0x08, 0]),
+            (b"ANIM", &[0; 6]),
+            (b"ANMF", &[0; 17]),
+            (b"ANMF", &[0; 17]),
+            (b"ANMF", &[0; 17]),
+        ]);
+        assert_eq!(
+            read_webp(Cursor::new(extended)),
+            Ok(ImageInfo {
+                format: ImageFormat::WebP,
+                width: 3840,
+                height: 2160,
+                frame_count: 3,
+                has_alpha: true,
+            })
+        );
+
+        // A lossless 100x200 image with alpha.
+        let bits: u32 = 99 | (199 << 14) | (1 << 28);
+        let mut lossless = vec![0x2f];
+        lossless.extend_from_slice(&bits.to_le_bytes());
+        let info = read_webp(Cursor::new(webp(&[(b"VP8L", &lossless)]))).unwrap();
+        assert_eq!((info.width, info.height), (100, 200));
+        assert_eq!(info.orientation(), Orientation::Portrait);
+        assert!(info.has_alpha);
+        assert!(!info.is_animated());
+
+        assert!(read_webp(Cursor::new(webp(&[]))).is_err());
+    }
+}
@@ -1,8 +1,22 @@
 mod file;
 mod file_list;
 mod filter;
+mod fuzzy;
+mod image_info;
+mod normalize;
 pub mod persist;
+mod query;
+mod session;
+mod slideshow;
+mod view;
 
 pub use self::file::*;
 pub use self::file_list::*;
 pub use self::filter::*;
+pub use self::fuzzy::*;
+pub use self::image_info::*;
+pub use self::normalize::*;
+pub use self::query::*;
+pub use self::session::*;
+pub use self::slideshow::*;
+pub use self::view::*;
@@ -0,0 +1,114 @@
+use unicode_normalization::char::canonical_combining_class;
+use unicode_normalization::UnicodeNormalization;
+
+/// Prepares a name for matching, as filesystems may store the same name differently.
+///
+/// Names are normalised to NFC, so `é` typed as one char matches `e` followed by a combining
+/// accent. When `is_folding`, accents are removed and full-width forms become their usual
+/// width, so `cafe` matches `café` and `ＩＭＧ` matches `IMG`.
+pub fn normalize(text: &str, is_case_sensitive: bool, is_folding: bool) -> String {
+    if text.is_ascii() {
+        return if is_case_sensitive {
+            text.to_owned()
+        } else {
+            text.to_ascii_lowercase()
+        };
+    }
+
+    normalize_chars(text, is_case_sensitive, is_folding)
+        .into_iter()
+        .map(|(c, _)| c)
+        .collect()
+}
+
+/// Normalises a name like `normalize`, pairing each char with the index of the char in `text`
+/// it came from, so matches can be highlighted in the original name.
+pub fn normalize_chars(
+    text: &str,
+    is_case_sensitive: bool,
+    is_folding: bool,
+) -> Vec<(char, usize)> {
+    // Most names are ASCII, which is already normalised and has one char per byte.
+    if text.is_ascii() {
+        return text
+            .bytes()
+            .enumerate()
+            .map(|(i, b)| {
+                let c = if is_case_sensitive {
+                    b
+                } else {
+                    b.to_ascii_lowercase()
+                };
+                (char::from(c), i)
+            })
+            .collect();
+    }
+
+    let chars: Vec<char> = text.chars().collect();
+    let mut normalized = Vec::with_capacity(chars.len());
+
+    // Chars only combine with the ones before them up to the last starter, so each run from one
+    // starter to the next can be normalised by itself.
+    let mut start = 0;
+    while start < chars.len() {
+        let end = (start + 1..chars.len())
+            .find(|&i| is_starter(chars[i]))
+            .unwrap_or(chars.len());
+        let run: String = chars[start..end].iter().collect();
+        let run: String = if is_folding {
+            run.nfkd().filter(|&c| !is_accent(c)).nfc().collect()
+        } else {
+            run.nfc().collect()
+        };
+        let run = if is_case_sensitive {
+            run
+        } else {
+            run.to_lowercase()
+        };
+
+        normalized.extend(run.chars().map(|c| (c, start)));
+        start = end;
+    }
+
+    normalized
+}
+
+/// Whether a char starts a new run, which Hangul vowels and final consonants don't, as they
+/// combine with the syllable before them.
+fn is_starter(c: char) -> bool {
+    canonical_combining_class(c) == 0 && !('\u{1160}'..='\u{11ff}').contains(&c)
+}
+
+/// Accents used with Latin, Greek and Cyrillic letters. Marks used by other scripts, such as the
+/// dakuten in `が`, change the letter rather than accent it, so they are kept.
+fn is_accent(c: char) -> bool {
+    ('\u{300}'..='\u{36f}').contains(&c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn normalize_composes_and_folds() {
+        let decomposed = "Cafe\u{301}.png";
+        assert_eq!(normalize(decomposed, true, false), "Caf\u{e9}.png");
+        assert_eq!(normalize(decomposed, false, true), "cafe.png");
+        assert_eq!(normalize("ＩＭＧ＿００１", false, true), "img_001");
+        assert_eq!(normalize("\u{304b}\u{3099}", true, true), "\u{304c}");
+        assert_eq!(
+            normalize("\u{1112}\u{1161}\u{11ab}", true, false),
+            "\u{d55c}"
+        );
+
+        let positions: Vec<_> = normalize_chars(decomposed, false, true)
+            .into_iter()
+            .map(|(_, i)| i)
+            .collect();
+        assert_eq!(positions, vec![0, 1, 2, 3, 5, 6, 7, 8]);
+        assert_eq!(
+            normalize_chars("A_b", false, true),
+            vec![('a', 0), ('_', 1), ('b', 2)]
+        );
+    }
+}
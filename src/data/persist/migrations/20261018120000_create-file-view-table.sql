@@ -0,0 +1,7 @@
+create table FileView
+( id       integer not null primary key autoincrement
+, name     text    not null unique
+, scale    real    not null
+, center_x real    not null
+, center_y real    not null
+);
\ No newline at end of file
@@ -0,0 +1,5 @@
+create table Setting
+( id    integer not null primary key autoincrement
+, key   text    not null unique
+, value text    not null
+);
\ No newline at end of file
@@ -0,0 +1,7 @@
+create table DirectoryState
+( id            integer not null primary key check (id = 1)
+, last_file     text        null
+, sort          text        null
+, filter_name   text        null
+, filter_rating integer     null
+);
\ No newline at end of file
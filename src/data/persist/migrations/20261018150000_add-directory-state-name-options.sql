@@ -0,0 +1,2 @@
+alter table DirectoryState add column filter_name_mode text null;
+alter table DirectoryState add column filter_case_sensitive integer null;
@@ -0,0 +1 @@
+alter table DirectoryState add column filter_rating_range text null;
@@ -0,0 +1,10 @@
+create table ImageInfo
+( id          integer not null primary key autoincrement
+, name        text    not null unique
+, modified    integer not null
+, format      text    not null
+, width       integer not null
+, height      integer not null
+, frame_count integer not null
+, has_alpha   boolean not null
+);
@@ -0,0 +1,8 @@
+create table SavedFilter
+( id                    integer not null primary key autoincrement
+, name                  text    not null unique
+, filter_name           text        null
+, filter_name_mode      text        null
+, filter_case_sensitive boolean     null
+, filter_rating_range   text        null
+);
@@ -0,0 +1 @@
+alter table DirectoryState add column filter_folding boolean null;
@@ -0,0 +1 @@
+alter table SavedFilter add column filter_folding boolean null;
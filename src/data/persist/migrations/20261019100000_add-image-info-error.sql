@@ -0,0 +1 @@
+alter table ImageInfo add column error text null;
@@ -3,9 +3,74 @@ extern crate migrant_lib;
 use std::path::Path;
 
 use crate::support::ErrToString;
-use migrant_lib::{Config, EmbeddedMigration, Migrator, Settings};
+use migrant_lib::{Config, EmbeddedMigration, Migratable, Migrator, Settings};
 
+/// Migrates the database stored in each image directory.
 pub fn migrate(db_file: &Path) -> Result<(), String> {
+    apply(
+        db_file,
+        &[
+            EmbeddedMigration::with_tag("20190112025101_create-file-table")
+                .up(include_str!("20190112025101_create-file-table.sql"))
+                .boxed(),
+            EmbeddedMigration::with_tag("20261018120000_create-file-view-table")
+                .up(include_str!("20261018120000_create-file-view-table.sql"))
+                .boxed(),
+            EmbeddedMigration::with_tag("20261018140000_create-directory-state-table")
+                .up(include_str!(
+                    "20261018140000_create-directory-state-table.sql"
+                ))
+                .boxed(),
+            EmbeddedMigration::with_tag("20261018150000_add-directory-state-name-options")
+                .up(include_str!(
+                    "20261018150000_add-directory-state-name-options.sql"
+                ))
+                .boxed(),
+            EmbeddedMigration::with_tag("20261018160000_add-directory-state-rating-filter")
+                .up(include_str!(
+                    "20261018160000_add-directory-state-rating-filter.sql"
+                ))
+                .boxed(),
+            EmbeddedMigration::with_tag("20261018170000_create-image-info-table")
+                .up(include_str!("20261018170000_create-image-info-table.sql"))
+                .boxed(),
+            EmbeddedMigration::with_tag("20261018180000_create-saved-filter-table")
+                .up(include_str!("20261018180000_create-saved-filter-table.sql"))
+                .boxed(),
+            EmbeddedMigration::with_tag("20261019090000_add-directory-state-folding")
+                .up(include_str!(
+                    "20261019090000_add-directory-state-folding.sql"
+                ))
+                .boxed(),
+            EmbeddedMigration::with_tag("20261019090100_add-saved-filter-folding")
+                .up(include_str!("20261019090100_add-saved-filter-folding.sql"))
+                .boxed(),
+            EmbeddedMigration::with_tag("20261019100000_add-image-info-error")
+                .up(include_str!("20261019100000_add-image-info-error.sql"))
+                .boxed(),
+        ],
+    )
+}
+
+/// Migrates the per-user settings database.
+pub fn migrate_settings(db_file: &Path) -> Result<(), String> {
+    apply(
+        db_file,
+        &[
+            EmbeddedMigration::with_tag("20261018130000_create-setting-table")
+                .up(include_str!("20261018130000_create-setting-table.sql"))
+                .boxed(),
+            EmbeddedMigration::with_tag("20261018180000_create-saved-filter-table")
+                .up(include_str!("20261018180000_create-saved-filter-table.sql"))
+                .boxed(),
+            EmbeddedMigration::with_tag("20261019090100_add-saved-filter-folding")
+                .up(include_str!("20261019090100_add-saved-filter-folding.sql"))
+                .boxed(),
+        ],
+    )
+}
+
+fn apply(db_file: &Path, migrations: &[Box<dyn Migratable>]) -> Result<(), String> {
     log::info!("Migrating database: {}", db_file.display());
 
     let settings = Settings::configure_sqlite()
@@ -20,13 +85,7 @@ pub fn migrate(db_file: &Path) -> Result<(), String> {
     config.setup().err_to_string()?;
 
     log::info!("Loading migrations...");
-    config
-        .use_migrations(&[
-            EmbeddedMigration::with_tag("20190112025101_create-file-table")
-                .up(include_str!("20190112025101_create-file-table.sql"))
-                .boxed(),
-        ])
-        .err_to_string()?;
+    config.use_migrations(migrations).err_to_string()?;
 
     let config = config.reload().err_to_string()?;
 
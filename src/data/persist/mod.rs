@@ -1,18 +1,35 @@
 extern crate rusqlite;
 
 mod migrations;
+mod settings;
 
-use crate::data::{File, Rating};
+pub use self::settings::SettingsManager;
+
+use crate::data::view::find_by_label;
+use crate::data::{
+    File, FileSort, Filter, ImageInfo, NameOptions, Rating, RatingFilter, SavedFilter, SavedView,
+    FILE_SORT_METHODS, IMAGE_FORMATS, NAME_MODES,
+};
 use crate::support::ErrToString;
 use rusqlite::types::{ToSql, ToSqlOutput, Value};
-use rusqlite::{Connection, Error, NO_PARAMS};
+use rusqlite::{Connection, Error, OptionalExtension, NO_PARAMS};
 use std::path::Path;
+use std::time::UNIX_EPOCH;
 
 #[derive(Debug)]
 pub struct PersistenceManager {
     conn: Connection,
 }
 
+/// Where the user left off in a directory.
+#[derive(Debug, Clone, Default)]
+pub struct DirectoryState {
+    /// The name of the last viewed file.
+    pub last_file: Option<String>,
+    pub sort: Option<FileSort>,
+    pub filter: Filter,
+}
+
 // lifetime
 impl PersistenceManager {
     pub fn open_dir(dir: &Path) -> Result<Self, String> {
@@ -41,8 +58,41 @@ impl PersistenceManager {
             .err_to_string()
     }
 
+    pub fn set_view(&self, file: &File, view: &Option<SavedView>) -> Result<(), String> {
+        match view {
+            Some(view) => self.conn.execute(
+                "INSERT OR REPLACE INTO FileView (name, scale, center_x, center_y) VALUES (?1, ?2, ?3, ?4)",
+                &[&file.name() as &dyn ToSql, &view.scale, &view.center_x, &view.center_y],
+            ),
+            None => self.conn.execute(
+                "DELETE FROM FileView WHERE name = ?1",
+                &[&file.name() as &dyn ToSql],
+            ),
+        }
+        .map(|_| ())
+        .err_to_string()
+    }
+
     pub fn populate_files(&self, files: &mut Vec<File>) -> Result<(), String> {
         use std::collections::HashMap;
+        let views: HashMap<String, _> = self
+            .conn
+            .prepare("SELECT name, scale, center_x, center_y FROM FileView")
+            .err_to_string()?
+            .query_map(NO_PARAMS, |row| {
+                (
+                    row.get::<_, String>(0),
+                    SavedView {
+                        scale: row.get(1),
+                        center_x: row.get(2),
+                        center_y: row.get(3),
+                    },
+                )
+            })
+            .err_to_string()?
+            .filter_map(|result| result.ok())
+            .collect();
+
         let results: HashMap<String, _> = self
             .conn
             .prepare("SELECT name, rating FROM File")
@@ -54,14 +104,223 @@ impl PersistenceManager {
             .filter_map(|result| result.ok())
             .collect();
 
+        let infos: HashMap<String, _> = self
+            .conn
+            .prepare(
+                "SELECT name, modified, format, width, height, frame_count, has_alpha, error FROM ImageInfo",
+            )
+            .err_to_string()?
+            .query_map(NO_PARAMS, |row| {
+                // Files which couldn't be read are remembered too, so they aren't read again.
+                let info = match row.get::<_, Option<String>>(7) {
+                    Some(error) => Some(Err(error)),
+                    None => find_by_label(IMAGE_FORMATS, &row.get::<_, String>(2)).map(|format| {
+                        Ok(ImageInfo {
+                            format,
+                            width: row.get::<_, i64>(3) as u32,
+                            height: row.get::<_, i64>(4) as u32,
+                            frame_count: row.get::<_, i64>(5) as u32,
+                            has_alpha: row.get(6),
+                        })
+                    }),
+                };
+                (row.get::<_, String>(0), (row.get::<_, i64>(1), info))
+            })
+            .err_to_string()?
+            .filter_map(|result| result.ok())
+            .collect();
+
         for file in files {
             if let Some(rating) = results.get(&file.name()) {
                 file.rating = rating.map(&Rating::from);
             }
+            file.view = views.get(&file.name()).cloned();
+            // Files changed since their headers were read need to be read again.
+            file.info = match infos.get(&file.name()) {
+                Some((modified, info)) if *modified == modified_secs(file) => info.clone(),
+                _ => None,
+            };
         }
 
         Ok(())
     }
+
+    /// Caches the header info of each file, or why it couldn't be read, so it isn't read again.
+    pub fn set_image_info(&mut self, files: &[File]) -> Result<(), String> {
+        let transaction = self.conn.transaction().err_to_string()?;
+        for file in files {
+            let (info, error) = match &file.info {
+                Some(Ok(info)) => (Some(info), None),
+                Some(Err(e)) => (None, Some(e)),
+                None => continue,
+            };
+            transaction
+                .execute(
+                    "INSERT OR REPLACE INTO ImageInfo (name, modified, format, width, height, frame_count, has_alpha, error) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                    &[
+                        &file.name() as &dyn ToSql,
+                        &modified_secs(file),
+                        &info.map_or("", |i| i.format.as_ref()),
+                        &info.map_or(0, |i| i.width),
+                        &info.map_or(0, |i| i.height),
+                        &info.map_or(0, |i| i.frame_count),
+                        &info.is_some_and(|i| i.has_alpha),
+                        &error,
+                    ],
+                )
+                .err_to_string()?;
+        }
+        transaction.commit().err_to_string()
+    }
+}
+
+fn modified_secs(file: &File) -> i64 {
+    file.last_modified()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+// directory state
+impl PersistenceManager {
+    pub fn directory_state(&self) -> Result<DirectoryState, String> {
+        let state = self
+            .conn
+            .query_row(
+                "SELECT last_file, sort, filter_name, filter_rating, filter_name_mode, filter_case_sensitive, filter_rating_range, filter_folding FROM DirectoryState WHERE id = 1",
+                NO_PARAMS,
+                |row| {
+                    let sort = row
+                        .get::<_, Option<String>>(1)
+                        .and_then(|s| find_by_label(FILE_SORT_METHODS, &s));
+                    let filter_rating = row.get::<_, Option<String>>(6);
+                    let filter = read_filter(
+                        row.get(2),
+                        row.get(4),
+                        row.get(5),
+                        row.get(7),
+                        filter_rating.as_ref().and_then(|r| r.parse().ok()),
+                    );
+                    // Older versions saved only the minimum rating.
+                    let filter = match filter_rating {
+                        Some(_) => filter,
+                        None => filter.with_rating(&row.get::<_, Option<i64>>(3).map(Rating::from)),
+                    };
+
+                    DirectoryState {
+                        last_file: row.get(0),
+                        sort,
+                        filter,
+                    }
+                },
+            )
+            .optional()
+            .err_to_string()?;
+
+        Ok(state.unwrap_or_default())
+    }
+
+    pub fn set_directory_state(&self, state: &DirectoryState) -> Result<(), String> {
+        self.conn
+            .execute(
+                "INSERT OR REPLACE INTO DirectoryState (id, last_file, sort, filter_name, filter_rating_range, filter_name_mode, filter_case_sensitive, filter_folding) VALUES (1, ?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                &[
+                    &state.last_file as &dyn ToSql,
+                    &state.sort.map(|s| s.as_ref().to_owned()),
+                    &state.filter.name(),
+                    &state.filter.rating().map(|r| r.to_string()),
+                    &state.filter.name_options().mode.as_ref(),
+                    &state.filter.name_options().is_case_sensitive,
+                    &state.filter.name_options().is_folding,
+                ],
+            )
+            .map(|_| ())
+            .err_to_string()
+    }
+}
+
+// saved filters
+impl PersistenceManager {
+    /// The filters saved for this directory.
+    pub fn saved_filters(&self) -> Result<Vec<SavedFilter>, String> {
+        saved_filters(&self.conn, false)
+    }
+
+    pub fn save_filter(&self, saved: &SavedFilter) -> Result<(), String> {
+        save_filter(&self.conn, saved)
+    }
+
+    pub fn delete_saved_filter(&self, name: &str) -> Result<(), String> {
+        delete_saved_filter(&self.conn, name)
+    }
+}
+
+/// Rebuilds a filter from the columns it's saved in.
+fn read_filter(
+    name: Option<String>,
+    name_mode: Option<String>,
+    is_case_sensitive: Option<bool>,
+    is_folding: Option<bool>,
+    rating: Option<RatingFilter>,
+) -> Filter {
+    let mut name_options = NameOptions::default();
+    if let Some(mode) = name_mode.and_then(|m| find_by_label(NAME_MODES, &m)) {
+        name_options.mode = mode;
+    }
+    name_options.is_case_sensitive = is_case_sensitive.unwrap_or(false);
+    name_options.is_folding = is_folding.unwrap_or(false);
+
+    Filter::default()
+        .with_name_options(name_options)
+        .with_name(&name.unwrap_or_default())
+        .with_rating_filter(rating)
+}
+
+// The directory and settings databases both have a SavedFilter table.
+fn saved_filters(conn: &Connection, is_global: bool) -> Result<Vec<SavedFilter>, String> {
+    let saved = conn
+        .prepare(
+            "SELECT name, filter_name, filter_name_mode, filter_case_sensitive, filter_rating_range, filter_folding FROM SavedFilter ORDER BY name COLLATE NOCASE",
+        )
+        .err_to_string()?
+        .query_map(NO_PARAMS, |row| {
+            let rating = row
+                .get::<_, Option<String>>(4)
+                .and_then(|r| r.parse().ok());
+            SavedFilter {
+                name: row.get(0),
+                filter: read_filter(row.get(1), row.get(2), row.get(3), row.get(5), rating),
+                is_global,
+            }
+        })
+        .err_to_string()?
+        .filter_map(|result| result.ok())
+        .collect();
+
+    Ok(saved)
+}
+
+fn save_filter(conn: &Connection, saved: &SavedFilter) -> Result<(), String> {
+    let filter = &saved.filter;
+    conn.execute(
+        "INSERT OR REPLACE INTO SavedFilter (name, filter_name, filter_name_mode, filter_case_sensitive, filter_rating_range, filter_folding) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        &[
+            &saved.name as &dyn ToSql,
+            &filter.name(),
+            &filter.name_options().mode.as_ref(),
+            &filter.name_options().is_case_sensitive,
+            &filter.rating().map(|r| r.to_string()),
+            &filter.name_options().is_folding,
+        ],
+    )
+    .map(|_| ())
+    .err_to_string()
+}
+
+fn delete_saved_filter(conn: &Connection, name: &str) -> Result<(), String> {
+    conn.execute("DELETE FROM SavedFilter WHERE name = ?1", &[name])
+        .map(|_| ())
+        .err_to_string()
 }
 
 impl ToSql for Rating {
@@ -69,3 +328,34 @@ impl ToSql for Rating {
         Ok(ToSqlOutput::Owned(Value::Integer(self.as_i64())))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::NameMode;
+
+    #[test]
+    pub fn read_filter_restores_saved_filter() {
+        let options = |mode| NameOptions {
+            mode,
+            is_case_sensitive: true,
+            ..NameOptions::default()
+        };
+        // The regex options are rejected, so the options saved are the ones the query used.
+        let filter = Filter::default()
+            .with_name_options(options(NameMode::Substring))
+            .with_name("File[")
+            .with_name_options(options(NameMode::Regex))
+            .with_rating_filter(Some(RatingFilter::Between(Rating::from(2), Rating::from(4))));
+
+        let name_options = filter.name_options();
+        let read = read_filter(
+            filter.name().map(str::to_owned),
+            Some(name_options.mode.as_ref().to_owned()),
+            Some(name_options.is_case_sensitive),
+            Some(name_options.is_folding),
+            filter.rating().cloned(),
+        );
+        assert_eq!(read, filter);
+    }
+}
@@ -0,0 +1,98 @@
+use rusqlite::{Connection, OptionalExtension};
+use std::fmt::Display;
+use std::path::Path;
+use std::str::FromStr;
+
+use super::migrations;
+use crate::data::SavedFilter;
+use crate::support::ErrToString;
+
+/// Stores settings which apply to every directory in a per-user database.
+#[derive(Debug)]
+pub struct SettingsManager {
+    conn: Connection,
+}
+
+// lifetime
+impl SettingsManager {
+    /// Opens the settings database in the user's config directory.
+    pub fn open_default() -> Result<Self, String> {
+        let dir = dirs::config_dir()
+            .ok_or_else(|| "Could not find the user config directory".to_owned())?
+            .join("aspect");
+        std::fs::create_dir_all(&dir).err_to_string()?;
+
+        SettingsManager::open(&dir.join("settings.sqlite"))
+    }
+
+    pub fn open(db_file: &Path) -> Result<Self, String> {
+        migrations::migrate_settings(db_file)?;
+        let conn = Connection::open(db_file).err_to_string()?;
+
+        Ok(SettingsManager { conn })
+    }
+
+    pub fn close(self) -> Result<(), String> {
+        self.conn.close().map_err(|(_, e)| format!("{}", e))
+    }
+}
+
+// values
+impl SettingsManager {
+    pub fn get(&self, key: &str) -> Result<Option<String>, String> {
+        self.conn
+            .query_row("SELECT value FROM Setting WHERE key = ?1", &[key], |row| {
+                row.get(0)
+            })
+            .optional()
+            .err_to_string()
+    }
+
+    /// Gets and parses a value, logging any failures.
+    pub fn get_parsed<T>(&self, key: &str) -> Option<T>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        match self.get(key) {
+            Ok(Some(value)) => match value.parse() {
+                Ok(value) => Some(value),
+                Err(e) => {
+                    log::warn!("Ignoring invalid setting {} = {}: {}", key, value, e);
+                    None
+                }
+            },
+            Ok(None) => None,
+            Err(e) => {
+                log::error!("Failed to read setting {}: {}", key, e);
+                None
+            }
+        }
+    }
+
+    pub fn set(&self, key: &str, value: &str) -> Result<(), String> {
+        self.conn
+            .execute(
+                "INSERT OR REPLACE INTO Setting (key, value) VALUES (?1, ?2)",
+                &[key, value],
+            )
+            .map(|_| ())
+            .err_to_string()
+    }
+}
+
+// saved filters
+impl SettingsManager {
+    /// The filters saved for every directory.
+    pub fn saved_filters(&self) -> Result<Vec<SavedFilter>, String> {
+        super::saved_filters(&self.conn, true)
+    }
+
+    pub fn save_filter(&self, saved: &SavedFilter) -> Result<(), String> {
+        super::save_filter(&self.conn, saved)
+    }
+
+    pub fn delete_saved_filter(&self, name: &str) -> Result<(), String> {
+        super::delete_saved_filter(&self.conn, name)
+    }
+}
@@ -0,0 +1,1124 @@
+use chrono::{DateTime, Datelike, Local, NaiveDate};
+use regex::{Regex, RegexBuilder};
+use std::convert::{AsRef, TryFrom};
+use std::fmt;
+
+use super::view::find_by_label;
+use super::{
+    fold_case, fuzzy_match, is_subsequence, normalize, normalize_chars, File, FuzzyMatch,
+    ImageFormat, Orientation, IMAGE_FORMATS,
+};
+
+/// A filter typed into the filter box, such as `rating>=3 ext:png,jpg -name:draft`.
+///
+/// Terms separated by spaces must all match, `OR` (or `|`) matches either side, `-` (or `NOT`)
+/// negates a term and parentheses group terms. A word without a `key:` matches the file name.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Query {
+    /// Matches when every query matches.
+    All(Vec<Query>),
+    /// Matches when any query matches.
+    Any(Vec<Query>),
+    Not(Box<Query>),
+    Term(Term),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Term {
+    Name(NamePattern),
+    /// The extension is one of these, lowercase and without the dot.
+    Extension(Vec<String>),
+    /// Unrated files never match, `rating:any` is an unbounded range.
+    Rating(Range<usize>),
+    /// Files which haven't been rated, from `rating:none`.
+    Unrated,
+    /// The file size in bytes.
+    Size(Range<u64>),
+    Modified(Range<NaiveDate>),
+    Width(Range<u32>),
+    Height(Range<u32>),
+    /// The length of the shorter side, so `res>=4k` is at least 2160 pixels.
+    Resolution(Range<u32>),
+    Orientation(Orientation),
+    Animated,
+    /// The image has an alpha channel, even if every pixel is opaque.
+    Alpha,
+    /// The format read from the file, which may not match its extension.
+    Format(Vec<ImageFormat>),
+}
+
+/// An inclusive range which may be open at either end.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Range<T> {
+    pub min: Option<T>,
+    pub max: Option<T>,
+}
+
+impl<T: PartialOrd + Copy> Range<T> {
+    fn contains(&self, value: T) -> bool {
+        self.min.is_none_or(|min| min <= value) && self.max.is_none_or(|max| value <= max)
+    }
+
+    /// Whether every value in this range is also in `other`.
+    fn is_within(&self, other: &Range<T>) -> bool {
+        let is_min_within = match (self.min, other.min) {
+            (_, None) => true,
+            (None, Some(_)) => false,
+            (Some(min), Some(other)) => other <= min,
+        };
+        let is_max_within = match (self.max, other.max) {
+            (_, None) => true,
+            (None, Some(_)) => false,
+            (Some(max), Some(other)) => max <= other,
+        };
+        is_min_within && is_max_within
+    }
+}
+
+/// How names in a query are matched against file names.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum NameMode {
+    /// The file name contains the text.
+    Substring,
+    /// The whole file name matches a pattern using `*`, `?` and `[...]`.
+    Glob,
+    /// The file name contains a match for a regular expression.
+    ///
+    /// Expressions using spaces, `|` or parentheses need to be quoted.
+    Regex,
+    /// The file name contains the chars in order, with the best matches ranked first.
+    Fuzzy,
+}
+
+pub static NAME_MODES: &[NameMode] = &[
+    NameMode::Substring,
+    NameMode::Glob,
+    NameMode::Regex,
+    NameMode::Fuzzy,
+];
+
+impl fmt::Display for NameMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_ref())
+    }
+}
+
+impl AsRef<str> for NameMode {
+    fn as_ref(&self) -> &str {
+        match self {
+            NameMode::Substring => "Text",
+            NameMode::Glob => "Glob",
+            NameMode::Regex => "Regex",
+            NameMode::Fuzzy => "Fuzzy",
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct NameOptions {
+    pub mode: NameMode,
+    pub is_case_sensitive: bool,
+    /// Whether accents and full-width forms are ignored, so `cafe` matches `café`.
+    pub is_folding: bool,
+}
+
+impl Default for NameOptions {
+    fn default() -> Self {
+        NameOptions {
+            mode: NameMode::Substring,
+            is_case_sensitive: false,
+            is_folding: false,
+        }
+    }
+}
+
+/// A name to match against file names, compiled for its `NameMode`.
+#[derive(Debug, Clone)]
+pub struct NamePattern {
+    /// The name as typed, normalised like the names it's matched against.
+    text: String,
+    options: NameOptions,
+    regex: Option<Regex>,
+    /// The chars of the text, for fuzzy matching.
+    chars: Vec<char>,
+}
+
+impl PartialEq for NamePattern {
+    fn eq(&self, other: &NamePattern) -> bool {
+        self.text == other.text && self.options == other.options
+    }
+}
+
+impl NamePattern {
+    pub fn new(text: &str, options: NameOptions) -> Result<Self, String> {
+        // Patterns keep their case, lowercasing would change escapes like `\D`.
+        let pattern = normalize(text, true, options.is_folding);
+        let regex = match options.mode {
+            NameMode::Substring | NameMode::Fuzzy => None,
+            NameMode::Glob => Some(
+                build_regex(&glob_to_regex(&pattern), options)
+                    .map_err(|e| format!("Invalid glob `{}`: {}", text, e))?,
+            ),
+            NameMode::Regex => Some(
+                build_regex(&pattern, options)
+                    .map_err(|e| format!("Invalid regular expression `{}`: {}", text, e))?,
+            ),
+        };
+        let text = normalize(text, options.is_case_sensitive, options.is_folding);
+
+        let chars = match options.mode {
+            NameMode::Fuzzy => pattern
+                .chars()
+                .map(|c| fold_case(c, options.is_case_sensitive))
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        Ok(NamePattern {
+            text,
+            options,
+            regex,
+            chars,
+        })
+    }
+
+    fn matches(&self, name: &str) -> bool {
+        let options = self.options;
+        if options.mode == NameMode::Fuzzy {
+            // Cased the same way as when highlighting, so every match can be highlighted.
+            let name = normalize(name, true, options.is_folding);
+            let chars = name
+                .chars()
+                .map(|c| fold_case(c, options.is_case_sensitive));
+            return is_subsequence(&self.chars, chars);
+        }
+
+        let name = normalize(name, options.is_case_sensitive, options.is_folding);
+        match &self.regex {
+            Some(regex) => regex.is_match(&name),
+            None => name.contains(self.text.as_str()),
+        }
+    }
+
+    fn implies(&self, other: &NamePattern) -> bool {
+        if self.options != other.options {
+            return false;
+        }
+        match self.options.mode {
+            NameMode::Substring => self.text.contains(other.text.as_str()),
+            // Names containing these chars in order also contain any subsequence of them.
+            NameMode::Fuzzy => is_subsequence(&other.chars, self.chars.iter().cloned()),
+            NameMode::Glob | NameMode::Regex => self.text == other.text,
+        }
+    }
+
+    /// Where the pattern best matches the name, if this is a fuzzy pattern.
+    fn fuzzy_match(&self, name: &str) -> Option<FuzzyMatch> {
+        if self.options.mode != NameMode::Fuzzy {
+            return None;
+        }
+
+        // Case is kept for scoring, which gives a bonus at the start of `camelCase` words.
+        let (chars, indices): (Vec<_>, Vec<_>) =
+            normalize_chars(name, true, self.options.is_folding)
+                .into_iter()
+                .unzip();
+        let mut found = fuzzy_match(&self.chars, &chars, self.options.is_case_sensitive)?;
+
+        // Each matched char highlights every char it was normalised from, such as an accent.
+        let len = name.chars().count();
+        let run = |start: usize| {
+            let end = indices.iter().find(|&&i| i > start).copied();
+            start..end.unwrap_or(len)
+        };
+        found.positions = found
+            .positions
+            .iter()
+            .flat_map(|&p| run(indices[p]))
+            .collect();
+        found.positions.dedup();
+        Some(found)
+    }
+}
+
+fn build_regex(pattern: &str, options: NameOptions) -> Result<Regex, String> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(!options.is_case_sensitive)
+        .build()
+        .map_err(|e| {
+            // Syntax errors span several lines pointing at the problem, the last says what it is.
+            let message = e.to_string();
+            let reason = message.lines().last().unwrap_or("");
+            reason.trim_start_matches("error: ").to_owned()
+        })
+}
+
+/// Converts a glob into a regular expression which matches the whole name.
+fn glob_to_regex(glob: &str) -> String {
+    let mut pattern = String::from("^");
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' => pattern.push_str(".*"),
+            '?' => pattern.push('.'),
+            '[' if chars.clone().any(|c| c == ']') => {
+                pattern.push('[');
+                if chars.peek() == Some(&'!') {
+                    chars.next();
+                    pattern.push('^');
+                }
+                for c in chars.by_ref() {
+                    if c == ']' {
+                        break;
+                    }
+                    if c == '\\' || c == '[' {
+                        pattern.push('\\');
+                    }
+                    pattern.push(c);
+                }
+                pattern.push(']');
+            }
+            _ => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    pattern.push('$');
+    pattern
+}
+
+impl Query {
+    /// Parses a query, describing what is wrong and where if it is invalid.
+    pub fn parse(text: &str, options: NameOptions) -> Result<Query, String> {
+        let tokens = tokenize(text)?;
+        let mut parser = Parser {
+            tokens,
+            position: 0,
+            end_column: text.chars().count() + 1,
+            options,
+        };
+        if parser.tokens.is_empty() {
+            return Ok(Query::All(Vec::new()));
+        }
+
+        let query = parser.parse_any()?;
+        match parser.next() {
+            Some((column, _)) => Err(format!("Unexpected `)` at column {}", column)),
+            None => Ok(query),
+        }
+    }
+
+    /// A query which matches file names containing `name`.
+    pub fn name(name: &str) -> Query {
+        let pattern =
+            NamePattern::new(name, NameOptions::default()).expect("substrings are always valid");
+        Query::Term(Term::Name(pattern))
+    }
+
+    pub fn matches(&self, file: &File) -> bool {
+        match self {
+            Query::All(queries) => queries.iter().all(|q| q.matches(file)),
+            Query::Any(queries) => queries.iter().any(|q| q.matches(file)),
+            Query::Not(query) => !query.matches(file),
+            Query::Term(term) => term.matches(file),
+        }
+    }
+
+    /// Whether every file this query matches is also matched by `other`.
+    ///
+    /// This is conservative, it may return false for some queries which do imply `other`.
+    pub fn implies(&self, other: &Query) -> bool {
+        match (self, other) {
+            (_, Query::All(others)) => others.iter().all(|o| self.implies(o)),
+            (Query::All(queries), _) => queries.iter().any(|q| q.implies(other)),
+            (_, Query::Any(others)) => others.iter().any(|o| self.implies(o)),
+            (Query::Any(queries), _) => queries.iter().all(|q| q.implies(other)),
+            (Query::Term(term), Query::Term(other)) => term.implies(other),
+            _ => self == other,
+        }
+    }
+
+    /// Whether the query reads image headers, which should be cached before matching many files.
+    pub fn needs_image_info(&self) -> bool {
+        match self {
+            Query::All(queries) | Query::Any(queries) => {
+                queries.iter().any(Query::needs_image_info)
+            }
+            Query::Not(query) => query.needs_image_info(),
+            Query::Term(term) => term.needs_image_info(),
+        }
+    }
+
+    /// Whether files should be ranked by `score`, because the query has fuzzy names.
+    pub fn is_ranked(&self) -> bool {
+        match self {
+            Query::All(queries) | Query::Any(queries) => queries.iter().any(Query::is_ranked),
+            Query::Not(_) => false,
+            Query::Term(Term::Name(pattern)) => pattern.options.mode == NameMode::Fuzzy,
+            Query::Term(_) => false,
+        }
+    }
+
+    /// How well a file name matches the fuzzy names in the query, higher is better.
+    pub fn score(&self, name: &str) -> i64 {
+        match self {
+            Query::All(queries) => queries.iter().map(|q| q.score(name)).sum(),
+            Query::Any(queries) => queries.iter().map(|q| q.score(name)).max().unwrap_or(0),
+            Query::Not(_) => 0,
+            Query::Term(Term::Name(pattern)) => pattern.fuzzy_match(name).map_or(0, |m| m.score),
+            Query::Term(_) => 0,
+        }
+    }
+
+    /// The index of each char in a file name matched by fuzzy names in the query.
+    pub fn highlights(&self, name: &str) -> Vec<usize> {
+        let mut positions = Vec::new();
+        self.collect_highlights(name, &mut positions);
+        positions.sort_unstable();
+        positions.dedup();
+        positions
+    }
+
+    fn collect_highlights(&self, name: &str, positions: &mut Vec<usize>) {
+        match self {
+            Query::All(queries) | Query::Any(queries) => {
+                for query in queries {
+                    query.collect_highlights(name, positions);
+                }
+            }
+            Query::Not(_) => (),
+            Query::Term(Term::Name(pattern)) => {
+                if let Some(found) = pattern.fuzzy_match(name) {
+                    positions.extend(found.positions);
+                }
+            }
+            Query::Term(_) => (),
+        }
+    }
+}
+
+impl Term {
+    fn matches(&self, file: &File) -> bool {
+        match self {
+            Term::Name(pattern) => pattern.matches(&file.name()),
+            Term::Extension(extensions) => file
+                .path
+                .extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|e| extensions.contains(&e.to_lowercase())),
+            Term::Rating(range) => match &file.rating {
+                Some(rating) => range.contains(rating.clone().into()),
+                None => false,
+            },
+            Term::Unrated => file.rating.is_none(),
+            Term::Size(range) => range.contains(file.size().bytes()),
+            Term::Modified(range) => {
+                let modified: DateTime<Local> = DateTime::from(file.last_modified());
+                range.contains(modified.date_naive())
+            }
+            Term::Width(range) => file.image_info().is_ok_and(|i| range.contains(i.width)),
+            Term::Height(range) => file.image_info().is_ok_and(|i| range.contains(i.height)),
+            Term::Resolution(range) => file
+                .image_info()
+                .is_ok_and(|i| range.contains(i.resolution())),
+            Term::Orientation(orientation) => file
+                .image_info()
+                .is_ok_and(|i| i.orientation() == *orientation),
+            Term::Animated => file.image_info().is_ok_and(|i| i.is_animated()),
+            Term::Alpha => file.image_info().is_ok_and(|i| i.has_alpha),
+            Term::Format(formats) => file.image_info().is_ok_and(|i| formats.contains(&i.format)),
+        }
+    }
+
+    fn needs_image_info(&self) -> bool {
+        matches!(
+            self,
+            Term::Width(_)
+                | Term::Height(_)
+                | Term::Resolution(_)
+                | Term::Orientation(_)
+                | Term::Animated
+                | Term::Alpha
+                | Term::Format(_)
+        )
+    }
+
+    fn implies(&self, other: &Term) -> bool {
+        match (self, other) {
+            (Term::Name(pattern), Term::Name(other)) => pattern.implies(other),
+            (Term::Extension(extensions), Term::Extension(other)) => {
+                extensions.iter().all(|e| other.contains(e))
+            }
+            (Term::Rating(range), Term::Rating(other)) => range.is_within(other),
+            (Term::Unrated, Term::Unrated) => true,
+            (Term::Size(range), Term::Size(other)) => range.is_within(other),
+            (Term::Modified(range), Term::Modified(other)) => range.is_within(other),
+            (Term::Width(range), Term::Width(other)) => range.is_within(other),
+            (Term::Height(range), Term::Height(other)) => range.is_within(other),
+            (Term::Resolution(range), Term::Resolution(other)) => range.is_within(other),
+            (Term::Orientation(orientation), Term::Orientation(other)) => orientation == other,
+            (Term::Animated, Term::Animated) | (Term::Alpha, Term::Alpha) => true,
+            (Term::Format(formats), Term::Format(other)) => {
+                formats.iter().all(|f| other.contains(f))
+            }
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    /// A term, `is_literal` if it started with a quote and is only a name.
+    Word {
+        text: String,
+        is_literal: bool,
+    },
+    Open,
+    Close,
+    Or,
+    Not,
+}
+
+/// Splits a query into tokens paired with the column they start at.
+fn tokenize(text: &str) -> Result<Vec<(usize, Token)>, String> {
+    let chars: Vec<char> = text.chars().collect();
+    let is_separator = |c: char| c.is_whitespace() || "()|".contains(c);
+
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let column = i + 1;
+        let token = match chars[i] {
+            c if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '|' => Token::Or,
+            '-' if chars.get(i + 1).is_some_and(|&c| !c.is_whitespace()) => Token::Not,
+            _ => {
+                let is_literal = chars[i] == '"';
+                let mut word = String::new();
+                while i < chars.len() && !is_separator(chars[i]) {
+                    if chars[i] == '"' {
+                        let length = chars[i + 1..]
+                            .iter()
+                            .position(|&c| c == '"')
+                            .ok_or_else(|| format!("Unclosed quote at column {}", i + 1))?;
+                        word.extend(&chars[i + 1..i + 1 + length]);
+                        i += length + 2;
+                    } else {
+                        word.push(chars[i]);
+                        i += 1;
+                    }
+                }
+
+                match word.as_str() {
+                    "AND" if !is_literal => continue,
+                    "OR" if !is_literal => tokens.push((column, Token::Or)),
+                    "NOT" if !is_literal => tokens.push((column, Token::Not)),
+                    _ => tokens.push((
+                        column,
+                        Token::Word {
+                            text: word,
+                            is_literal,
+                        },
+                    )),
+                }
+                continue;
+            }
+        };
+
+        tokens.push((column, token));
+        i += 1;
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    position: usize,
+    end_column: usize,
+    options: NameOptions,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(_, token)| token)
+    }
+
+    fn next(&mut self) -> Option<(usize, Token)> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    /// Where the next token starts, for error messages.
+    fn location(&self) -> String {
+        match self.tokens.get(self.position) {
+            Some((column, _)) => format!("at column {}", column),
+            None => format!("at the end (column {})", self.end_column),
+        }
+    }
+
+    fn parse_any(&mut self) -> Result<Query, String> {
+        let mut queries = vec![self.parse_all()?];
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            queries.push(self.parse_all()?);
+        }
+        Ok(flatten(queries, Query::Any))
+    }
+
+    fn parse_all(&mut self) -> Result<Query, String> {
+        let mut queries = Vec::new();
+        while let Some(token) = self.peek() {
+            if *token == Token::Or || *token == Token::Close {
+                break;
+            }
+            queries.push(self.parse_unary()?);
+        }
+
+        if queries.is_empty() {
+            return Err(format!("Expected a filter {}", self.location()));
+        }
+        Ok(flatten(queries, Query::All))
+    }
+
+    fn parse_unary(&mut self) -> Result<Query, String> {
+        let location = self.location();
+        match self.next() {
+            Some((_, Token::Not)) => Ok(Query::Not(Box::new(self.parse_unary()?))),
+            Some((column, Token::Open)) => {
+                let query = self.parse_any()?;
+                match self.next() {
+                    Some((_, Token::Close)) => Ok(query),
+                    _ => Err(format!("Missing `)` for the `(` at column {}", column)),
+                }
+            }
+            Some((column, Token::Word { text, is_literal })) => {
+                parse_term(&text, is_literal, self.options)
+                    .map_err(|e| format!("{} at column {}", e, column))
+            }
+            _ => Err(format!("Expected a filter {}", location)),
+        }
+    }
+}
+
+/// Avoids wrapping a single query in a group.
+fn flatten(mut queries: Vec<Query>, group: fn(Vec<Query>) -> Query) -> Query {
+    if queries.len() == 1 {
+        queries.remove(0)
+    } else {
+        group(queries)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Op {
+    Is,
+    Greater,
+    GreaterOrEqual,
+    Less,
+    LessOrEqual,
+}
+
+static OPS: &[(&str, Op)] = &[
+    (">=", Op::GreaterOrEqual),
+    ("<=", Op::LessOrEqual),
+    (":", Op::Is),
+    ("=", Op::Is),
+    (">", Op::Greater),
+    ("<", Op::Less),
+];
+
+fn parse_term(word: &str, is_literal: bool, options: NameOptions) -> Result<Query, String> {
+    let name = |name: &str| NamePattern::new(name, options).map(|p| Query::Term(Term::Name(p)));
+
+    let split = word.find(|c| ":<>=".contains(c)).filter(|_| !is_literal);
+    let (key, op_text, op, value) = match split {
+        Some(i) => {
+            let rest = &word[i..];
+            let &(op_text, op) = OPS
+                .iter()
+                .find(|(text, _)| rest.starts_with(text))
+                .expect("the word was split at an operator");
+            (&word[..i], op_text, op, &rest[op_text.len()..])
+        }
+        None => return name(word),
+    };
+
+    if key.is_empty() {
+        return Err(format!("Missing a filter name before `{}`", op_text));
+    }
+    if value.is_empty() {
+        return Err(format!("Missing a value after `{}{}`", key, op_text));
+    }
+
+    let term = match key.to_lowercase().as_str() {
+        "name" => {
+            if op != Op::Is {
+                return Err(format!("`name` can't be compared with `{}`", op_text));
+            }
+            // Commas are part of regular expressions, like `\d{1,3}`.
+            let names: Vec<_> = match options.mode {
+                NameMode::Regex => vec![value],
+                _ => value.split(',').filter(|n| !n.is_empty()).collect(),
+            };
+            let names = names.into_iter().map(name).collect::<Result<_, _>>()?;
+            return Ok(flatten(names, Query::Any));
+        }
+        "ext" | "extension" => {
+            if op != Op::Is {
+                return Err(format!("`{}` can't be compared with `{}`", key, op_text));
+            }
+            let extensions = value
+                .split(',')
+                .map(|e| e.trim_start_matches('.').to_lowercase())
+                .filter(|e| !e.is_empty())
+                .collect();
+            Term::Extension(extensions)
+        }
+        "rating" => match (op, value.to_lowercase().as_str()) {
+            (Op::Is, "none") => Term::Unrated,
+            (Op::Is, "any") => Term::Rating(Range {
+                min: None,
+                max: None,
+            }),
+            _ => Term::Rating(parse_rating_range(op, value)?),
+        },
+        "size" => Term::Size(parse_range(op, value, parse_size)?),
+        "modified" | "date" => Term::Modified(parse_range(op, value, parse_date)?),
+        "w" | "width" => Term::Width(parse_range(op, value, parse_number)?),
+        "h" | "height" => Term::Height(parse_range(op, value, parse_number)?),
+        "res" | "resolution" => Term::Resolution(parse_range(op, value, parse_resolution)?),
+        "format" | "is" | "has" if op != Op::Is => {
+            return Err(format!("`{}` can't be compared with `{}`", key, op_text))
+        }
+        "format" => Term::Format(
+            value
+                .split(',')
+                .filter(|f| !f.is_empty())
+                .map(parse_format)
+                .collect::<Result<_, _>>()?,
+        ),
+        "is" => match value.to_lowercase().as_str() {
+            "landscape" => Term::Orientation(Orientation::Landscape),
+            "portrait" => Term::Orientation(Orientation::Portrait),
+            "square" => Term::Orientation(Orientation::Square),
+            "animated" => Term::Animated,
+            _ => {
+                return Err(format!(
+                    "Unknown `is:{}`, expected landscape, portrait, square or animated",
+                    value
+                ))
+            }
+        },
+        "has" => match value.to_lowercase().as_str() {
+            "alpha" => Term::Alpha,
+            _ => return Err(format!("Unknown `has:{}`, expected alpha", value)),
+        },
+        // Other characters are allowed in file names, only `:` is reserved for filters.
+        _ if op_text != ":" => return name(word),
+        _ => {
+            return Err(format!(
+                "Unknown filter `{}`, expected name, ext, rating, size, modified, w, h, res, format, is or has",
+                key
+            ))
+        }
+    };
+
+    Ok(Query::Term(term))
+}
+
+/// Parses a comparison or range, where each value may cover several, like a month of dates.
+fn parse_range<T: Step>(
+    op: Op,
+    value: &str,
+    parse: fn(&str) -> Result<(T, T), String>,
+) -> Result<Range<T>, String> {
+    let range = match op {
+        Op::Is => match value.split_once("..") {
+            Some((min, max)) => {
+                let min = if min.is_empty() {
+                    None
+                } else {
+                    Some(parse(min)?.0)
+                };
+                let max = if max.is_empty() {
+                    None
+                } else {
+                    Some(parse(max)?.1)
+                };
+                if min.is_none() && max.is_none() {
+                    return Err("A range needs at least one end".to_owned());
+                }
+                Range { min, max }
+            }
+            None => {
+                let (min, max) = parse(value)?;
+                Range {
+                    min: Some(min),
+                    max: Some(max),
+                }
+            }
+        },
+        Op::GreaterOrEqual => Range {
+            min: Some(parse(value)?.0),
+            max: None,
+        },
+        Op::Greater => Range {
+            min: Some(
+                parse(value)?
+                    .1
+                    .next()
+                    .ok_or_else(|| format!("Nothing is after `{}`", value))?,
+            ),
+            max: None,
+        },
+        Op::LessOrEqual => Range {
+            min: None,
+            max: Some(parse(value)?.1),
+        },
+        Op::Less => Range {
+            min: None,
+            max: Some(
+                parse(value)?
+                    .0
+                    .prev()
+                    .ok_or_else(|| format!("Nothing is before `{}`", value))?,
+            ),
+        },
+    };
+
+    match (range.min, range.max) {
+        (Some(min), Some(max)) if max < min => Err(format!("The range `{}` is empty", value)),
+        _ => Ok(range),
+    }
+}
+
+/// Values which can be compared with `>` and `<` by stepping to the next or previous value.
+trait Step: PartialOrd + Copy {
+    fn next(self) -> Option<Self>;
+    fn prev(self) -> Option<Self>;
+}
+
+macro_rules! impl_step {
+    ($($t:ty),*) => {
+        $(impl Step for $t {
+            fn next(self) -> Option<Self> {
+                self.checked_add(1)
+            }
+
+            fn prev(self) -> Option<Self> {
+                self.checked_sub(1)
+            }
+        })*
+    };
+}
+
+impl_step!(usize, u32, u64);
+
+impl Step for NaiveDate {
+    fn next(self) -> Option<Self> {
+        self.succ_opt()
+    }
+
+    fn prev(self) -> Option<Self> {
+        self.pred_opt()
+    }
+}
+
+fn parse_number<T: std::str::FromStr + Copy>(value: &str) -> Result<(T, T), String> {
+    value
+        .parse()
+        .map(|n| (n, n))
+        .map_err(|_| format!("`{}` is not a whole number", value))
+}
+
+fn parse_rating(value: &str) -> Result<(usize, usize), String> {
+    let (rating, _) = parse_number::<usize>(value)?;
+    if rating < super::Rating::min_value() || rating > super::Rating::max_value() {
+        return Err(format!("Ratings are from 1 to 5, not `{}`", value));
+    }
+    Ok((rating, rating))
+}
+
+/// Parses a rating range, which can't step past the lowest or highest rating.
+fn parse_rating_range(op: Op, value: &str) -> Result<Range<usize>, String> {
+    use super::Rating;
+
+    let range = parse_range(op, value, parse_rating)?;
+    if range.max.is_some_and(|max| max < Rating::min_value()) {
+        return Err(format!("Nothing is before `{}`", value));
+    }
+    if range.min.is_some_and(|min| min > Rating::max_value()) {
+        return Err(format!("Nothing is after `{}`", value));
+    }
+    Ok(range)
+}
+
+/// Parses a size like `2MB`, using the same 1024 based units as `FileSize`.
+fn parse_size(value: &str) -> Result<(u64, u64), String> {
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("`{}` is not a size", value))?;
+    let multiplier = match unit.to_lowercase().as_str() {
+        "" | "b" => 1u64,
+        "k" | "kb" => 1 << 10,
+        "m" | "mb" => 1 << 20,
+        "g" | "gb" => 1 << 30,
+        _ => {
+            return Err(format!(
+                "Unknown size unit `{}`, expected B, KB, MB or GB",
+                unit
+            ))
+        }
+    };
+
+    let bytes = (number * multiplier as f64).round() as u64;
+    Ok((bytes, bytes))
+}
+
+/// Parses a number of pixels or a named resolution like `1080p` or `4k`.
+fn parse_resolution(value: &str) -> Result<(u32, u32), String> {
+    let pixels = match value.to_lowercase().as_str() {
+        "720p" => 720,
+        "1080p" => 1080,
+        "1440p" => 1440,
+        "4k" | "2160p" => 2160,
+        "5k" => 2880,
+        "8k" | "4320p" => 4320,
+        _ => {
+            return parse_number(value).map_err(|_| {
+                format!(
+                    "Unknown resolution `{}`, expected pixels, 720p, 1080p, 1440p, 4k, 5k or 8k",
+                    value
+                )
+            })
+        }
+    };
+    Ok((pixels, pixels))
+}
+
+fn parse_format(value: &str) -> Result<ImageFormat, String> {
+    match value.trim_start_matches('.').to_lowercase().as_str() {
+        "jpg" => Ok(ImageFormat::Jpeg),
+        format => find_by_label(IMAGE_FORMATS, format).ok_or_else(|| {
+            format!(
+                "Unknown format `{}`, expected png, jpeg, gif, bmp or webp",
+                value
+            )
+        }),
+    }
+}
+
+/// Parses `YYYY`, `YYYY-MM` or `YYYY-MM-DD` into the first and last day it covers.
+fn parse_date(value: &str) -> Result<(NaiveDate, NaiveDate), String> {
+    let invalid = || {
+        format!(
+            "`{}` is not a date, expected YYYY, YYYY-MM or YYYY-MM-DD",
+            value
+        )
+    };
+    let parts = value
+        .split('-')
+        .map(|p| p.parse::<u32>().ok())
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(invalid)?;
+    let (&year, rest) = parts.split_first().ok_or_else(invalid)?;
+    let year = i32::try_from(year).map_err(|_| invalid())?;
+
+    let dates = match *rest {
+        [] => NaiveDate::from_ymd_opt(year, 1, 1).zip(NaiveDate::from_ymd_opt(year, 12, 31)),
+        // The first of the month is checked first, so the year and month are in range.
+        [month] => NaiveDate::from_ymd_opt(year, month, 1).and_then(|first| {
+            let next_month = match month {
+                12 => NaiveDate::from_ymd_opt(year + 1, 1, 1),
+                _ => NaiveDate::from_ymd_opt(year, month + 1, 1),
+            };
+            Some((first, next_month?.pred_opt()?))
+        }),
+        [month, day] => NaiveDate::from_ymd_opt(year, month, day).map(|d| (d, d)),
+        _ => None,
+    };
+
+    dates
+        .filter(|(first, _)| first.year() > 0)
+        .ok_or_else(invalid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{ImageInfo, Rating};
+    use std::path::PathBuf;
+
+    fn parse(text: &str) -> Result<Query, String> {
+        Query::parse(text, NameOptions::default())
+    }
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    pub fn parse_example_query() {
+        let query =
+            parse("rating>=3 ext:png,jpg size>2MB modified:2019-01..2019-06 -name:draft w>=1920")
+                .unwrap();
+
+        assert_eq!(
+            query,
+            Query::All(vec![
+                Query::Term(Term::Rating(Range {
+                    min: Some(3),
+                    max: None
+                })),
+                Query::Term(Term::Extension(vec!["png".to_owned(), "jpg".to_owned()])),
+                Query::Term(Term::Size(Range {
+                    min: Some(2 * 1024 * 1024 + 1),
+                    max: None
+                })),
+                Query::Term(Term::Modified(Range {
+                    min: Some(date(2019, 1, 1)),
+                    max: Some(date(2019, 6, 30))
+                })),
+                Query::Not(Box::new(Query::name("draft"))),
+                Query::Term(Term::Width(Range {
+                    min: Some(1920),
+                    max: None
+                })),
+            ])
+        );
+    }
+
+    #[test]
+    pub fn parse_groups_and_alternatives() {
+        let query = parse(r#"(cat OR dog) NOT "rating:5" | ext:.GIF"#).unwrap();
+        assert_eq!(
+            query,
+            Query::Any(vec![
+                Query::All(vec![
+                    Query::Any(vec![Query::name("cat"), Query::name("dog")]),
+                    Query::Not(Box::new(Query::name("rating:5"))),
+                ]),
+                Query::Term(Term::Extension(vec!["gif".to_owned()])),
+            ])
+        );
+        assert_eq!(parse("a=b.png").unwrap(), Query::name("a=b.png"));
+        assert_eq!(parse("  ").unwrap(), Query::All(Vec::new()));
+    }
+
+    #[test]
+    pub fn parse_describes_errors() {
+        let error = |text| parse(text).unwrap_err();
+        assert_eq!(
+            error("rating>=9"),
+            "Ratings are from 1 to 5, not `9` at column 1"
+        );
+        assert_eq!(
+            error("cat colour:red"),
+            "Unknown filter `colour`, expected name, ext, rating, size, modified, w, h, res, format, is or has at column 5"
+        );
+        assert_eq!(
+            error("size>2XB"),
+            "Unknown size unit `XB`, expected B, KB, MB or GB at column 1"
+        );
+        assert_eq!(
+            error("is:round"),
+            "Unknown `is:round`, expected landscape, portrait, square or animated at column 1"
+        );
+        assert_eq!(error("(cat"), "Missing `)` for the `(` at column 1");
+        assert_eq!(error("cat)"), "Unexpected `)` at column 4");
+        assert_eq!(error("cat OR"), "Expected a filter at the end (column 7)");
+        assert_eq!(error("w:"), "Missing a value after `w:` at column 1");
+        assert_eq!(error("w:10..5"), "The range `10..5` is empty at column 1");
+        assert_eq!(error(r#"name:"cat"#), "Unclosed quote at column 6");
+        assert_eq!(
+            error("modified:2019-13"),
+            "`2019-13` is not a date, expected YYYY, YYYY-MM or YYYY-MM-DD at column 1"
+        );
+        assert!(error("modified:2147483647-12").contains("is not a date"));
+        assert!(error("modified:2019-4294967295").contains("is not a date"));
+        assert!(error("modified:4294967295").contains("is not a date"));
+        assert_eq!(error("rating<1"), "Nothing is before `1` at column 1");
+        assert_eq!(error("rating>5"), "Nothing is after `5` at column 1");
+        assert!(parse("rating<2 modified:2019-12").is_ok());
+
+        let regex = NameOptions {
+            mode: NameMode::Regex,
+            ..NameOptions::default()
+        };
+        assert_eq!(
+            Query::parse(r#"cat "(dog""#, regex).unwrap_err(),
+            "Invalid regular expression `(dog`: unclosed group at column 5"
+        );
+    }
+
+    #[test]
+    pub fn matches_and_implies() {
+        let file = File {
+            path: PathBuf::from("holiday draft.PNG"),
+            rating: Some(Rating::from(4)),
+            view: None,
+            info: None,
+            metadata: None,
+        };
+        let matches = |text| parse(text).unwrap().matches(&file);
+        assert!(matches("holiday rating:3..5 ext:png,jpg"));
+        assert!(matches("beach | DRAFT"));
+        assert!(!matches("-name:draft"));
+        assert!(!matches("rating<4"));
+        assert!(matches("rating:any"));
+        assert!(!matches("rating:none"));
+
+        let implies = |a, b| parse(a).unwrap().implies(&parse(b).unwrap());
+        assert!(implies("holiday", "hol"));
+        assert!(implies("holiday rating>=4", "rating>=3"));
+        assert!(implies("ext:png", "ext:png,jpg"));
+        assert!(implies("cat -dog", "-dog"));
+        assert!(!implies("cat", "cat rating>=3"));
+        assert!(!implies("cat | dog", "cat"));
+        assert!(!implies("-dog", "-do"));
+    }
+
+    #[test]
+    pub fn matches_image_info() {
+        let file = File {
+            info: Some(Ok(ImageInfo {
+                format: ImageFormat::WebP,
+                width: 3840,
+                height: 2160,
+                frame_count: 12,
+                has_alpha: false,
+            })),
+            ..File::from("clip.webp")
+        };
+        let matches = |text| parse(text).unwrap().matches(&file);
+        assert!(matches("is:landscape res>=4k format:webp is:animated"));
+        assert!(matches("w:3840 h>=1080"));
+        assert!(!matches("is:square | has:alpha | res>4k | format:png,jpg"));
+
+        let unreadable = File {
+            info: Some(Err("Unsupported image type".to_owned())),
+            ..File::from("clip.png")
+        };
+        assert!(!parse("w>0 | is:landscape | has:alpha")
+            .unwrap()
+            .matches(&unreadable));
+        assert!(!parse("w>0").unwrap().matches(&File::from("unread.png")));
+
+        let implies = |a, b| parse(a).unwrap().implies(&parse(b).unwrap());
+        assert!(implies("res>=8k", "res>=1080p"));
+        assert!(implies("format:gif", "format:gif,webp"));
+        assert!(!implies("is:portrait", "is:landscape"));
+
+        assert!(parse("cat -is:animated").unwrap().needs_image_info());
+        assert!(!parse("cat rating>=3").unwrap().needs_image_info());
+    }
+}
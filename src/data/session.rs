@@ -0,0 +1,132 @@
+use std::path::PathBuf;
+
+use super::persist::SettingsManager;
+use super::FileList;
+use crate::support::LogError;
+use crate::systems::EventSystem;
+
+/// The window's position and size in logical pixels.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct WindowGeometry {
+    pub x: f64,
+    pub y: f64,
+    pub w: f64,
+    pub h: f64,
+}
+
+/// Where the user left off, saved on exit so the next run can resume it.
+#[derive(Debug, Clone, Default)]
+pub struct Session {
+    /// The geometry of the window when it isn't fullscreen.
+    pub window: Option<WindowGeometry>,
+    pub is_fullscreen: bool,
+    pub is_overlay_visible: bool,
+    /// The last directory, which keeps its own sort and filter.
+    pub directory: Option<PathBuf>,
+    /// The name of the file which was being viewed in `directory`.
+    pub file: Option<String>,
+}
+
+const WINDOW_KEY: &str = "session.window";
+const FULLSCREEN_KEY: &str = "session.fullscreen";
+const OVERLAY_KEY: &str = "session.overlay";
+const DIRECTORY_KEY: &str = "session.directory";
+const FILE_KEY: &str = "session.file";
+
+// persistence
+impl Session {
+    pub fn load(persist: &SettingsManager) -> Self {
+        let mut session = Session::default();
+
+        let get = |key| persist.get(key).log_err().and_then(|value| value);
+        session.window = get(WINDOW_KEY).and_then(|g| parse_geometry(&g));
+        session.directory = get(DIRECTORY_KEY)
+            .filter(|d| !d.is_empty())
+            .map(PathBuf::from);
+        session.file = get(FILE_KEY).filter(|f| !f.is_empty());
+
+        session.is_fullscreen = persist.get_parsed(FULLSCREEN_KEY).unwrap_or(false);
+        session.is_overlay_visible = persist.get_parsed(OVERLAY_KEY).unwrap_or(false);
+
+        session
+    }
+
+    pub fn save(&self, persist: &SettingsManager) -> Result<(), String> {
+        let window = self
+            .window
+            .map(|g| format!("{},{},{},{}", g.x, g.y, g.w, g.h));
+        let directory = self.directory.as_ref().and_then(|d| d.to_str());
+
+        persist.set(WINDOW_KEY, window.as_ref().map_or("", |w| w.as_str()))?;
+        persist.set(FULLSCREEN_KEY, &self.is_fullscreen.to_string())?;
+        persist.set(OVERLAY_KEY, &self.is_overlay_visible.to_string())?;
+        persist.set(DIRECTORY_KEY, directory.unwrap_or(""))?;
+        persist.set(FILE_KEY, self.file.as_ref().map_or("", |f| f.as_str()))
+    }
+}
+
+fn parse_geometry(geometry: &str) -> Option<WindowGeometry> {
+    let parts: Vec<f64> = geometry
+        .split(',')
+        .map(|p| p.trim().parse::<f64>().ok())
+        .collect::<Option<_>>()?;
+    match parts.as_slice() {
+        &[x, y, w, h] if w > 0.0 && h > 0.0 => Some(WindowGeometry { x, y, w, h }),
+        _ => None,
+    }
+}
+
+impl Session {
+    /// Reopens the last directory at the file which was being viewed, or where the directory
+    /// was left off if the file is gone. The sort and filter are restored with the directory.
+    pub fn restore_files(&self) -> Option<FileList> {
+        let directory = self.directory.as_ref()?;
+        log::info!("Resuming session in {}", directory.display());
+
+        self.file
+            .as_ref()
+            .and_then(|f| FileList::from_file(&directory.join(f)))
+            .or_else(|| FileList::from_dir(directory))
+    }
+
+    /// Records the current directory and file so they can be restored.
+    pub fn set_files(&mut self, files: &FileList) {
+        let path = files.current().map(|f| &f.path);
+        self.directory = path.and_then(|p| p.parent()).map(|d| d.to_path_buf());
+        self.file = path
+            .and_then(|p| p.file_name())
+            .map(|n| n.to_string_lossy().into_owned());
+    }
+
+    pub fn update(&mut self, events: &EventSystem) {
+        use crate::systems::events::*;
+
+        for event in events.events() {
+            if let AppEvent::Window(Window::Overlay(is_visible)) = event {
+                self.is_overlay_visible = *is_visible;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn parse_geometry_requires_positive_size() {
+        assert_eq!(
+            parse_geometry("-10,20.5,800,600"),
+            Some(WindowGeometry {
+                x: -10.0,
+                y: 20.5,
+                w: 800.0,
+                h: 600.0
+            })
+        );
+        assert_eq!(parse_geometry("0,0,0,600"), None);
+        assert_eq!(parse_geometry("0,0,800"), None);
+        assert_eq!(parse_geometry("0,0,800,600,1"), None);
+        assert_eq!(parse_geometry(""), None);
+    }
+}
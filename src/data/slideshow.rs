@@ -0,0 +1,254 @@
+use std::convert::AsRef;
+use std::fmt;
+use std::time::Duration;
+
+use super::persist::SettingsManager;
+use super::view::find_by_label;
+use super::Rating;
+use crate::support::LogError;
+use crate::systems::EventSystem;
+
+/// How the slideshow picks the next file.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum SlideshowOrder {
+    /// The next file in the list.
+    Sequential,
+    /// A random file, favoring higher ratings.
+    Weighted,
+}
+
+pub static SLIDESHOW_ORDERS: &[SlideshowOrder] =
+    &[SlideshowOrder::Sequential, SlideshowOrder::Weighted];
+
+impl fmt::Display for SlideshowOrder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_ref())
+    }
+}
+
+impl AsRef<str> for SlideshowOrder {
+    fn as_ref(&self) -> &str {
+        match self {
+            SlideshowOrder::Sequential => "In Order",
+            SlideshowOrder::Weighted => "Weighted by Rating",
+        }
+    }
+}
+
+/// Settings which control how the slideshow advances, saved as a user preference.
+#[derive(Debug, Clone)]
+pub struct SlideshowSettings {
+    /// How long each file is shown.
+    pub interval: Duration,
+    /// Start over from the first file instead of stopping after the last one.
+    pub repeat: bool,
+    /// Shuffle the files each time the slideshow starts over.
+    pub shuffle: bool,
+    /// Hold the current file while the user is moving the mouse or pressing keys.
+    pub pause_on_interaction: bool,
+    /// Let animated images finish playing once before moving on.
+    pub wait_for_animation: bool,
+    pub order: SlideshowOrder,
+    /// The relative chance of picking a file for `SlideshowOrder::Weighted`.
+    ///
+    /// The first weight is for unrated files, the rest are for each number of stars.
+    pub rating_weights: [u32; 6],
+    /// How many of the most recently shown files `SlideshowOrder::Weighted` avoids.
+    pub cooldown: usize,
+}
+
+impl Default for SlideshowSettings {
+    fn default() -> Self {
+        SlideshowSettings {
+            interval: Duration::from_secs(15),
+            repeat: true,
+            shuffle: false,
+            pause_on_interaction: true,
+            wait_for_animation: true,
+            order: SlideshowOrder::Sequential,
+            rating_weights: [1, 1, 2, 4, 8, 16],
+            cooldown: 10,
+        }
+    }
+}
+
+const INTERVAL_KEY: &str = "slideshow.interval";
+const REPEAT_KEY: &str = "slideshow.repeat";
+const SHUFFLE_KEY: &str = "slideshow.shuffle";
+const PAUSE_ON_INTERACTION_KEY: &str = "slideshow.pause_on_interaction";
+const WAIT_FOR_ANIMATION_KEY: &str = "slideshow.wait_for_animation";
+const ORDER_KEY: &str = "slideshow.order";
+const RATING_WEIGHTS_KEY: &str = "slideshow.rating_weights";
+const COOLDOWN_KEY: &str = "slideshow.cooldown";
+
+// persistence
+impl SlideshowSettings {
+    pub fn load(persist: &SettingsManager) -> Self {
+        let mut settings = SlideshowSettings::default();
+
+        if let Some(secs) = persist.get_parsed::<u64>(INTERVAL_KEY).filter(|&s| s > 0) {
+            settings.interval = Duration::from_secs(secs);
+        }
+        settings.repeat = persist.get_parsed(REPEAT_KEY).unwrap_or(settings.repeat);
+        settings.shuffle = persist.get_parsed(SHUFFLE_KEY).unwrap_or(settings.shuffle);
+        settings.pause_on_interaction = persist
+            .get_parsed(PAUSE_ON_INTERACTION_KEY)
+            .unwrap_or(settings.pause_on_interaction);
+        settings.wait_for_animation = persist
+            .get_parsed(WAIT_FOR_ANIMATION_KEY)
+            .unwrap_or(settings.wait_for_animation);
+
+        let get = |key| persist.get(key).log_err().and_then(|value| value);
+        if let Some(order) = get(ORDER_KEY).and_then(|l| find_by_label(SLIDESHOW_ORDERS, &l)) {
+            settings.order = order;
+        }
+        if let Some(weights) = get(RATING_WEIGHTS_KEY).and_then(|w| parse_weights(&w)) {
+            settings.rating_weights = weights;
+        }
+        settings.cooldown = persist
+            .get_parsed(COOLDOWN_KEY)
+            .unwrap_or(settings.cooldown);
+
+        settings
+    }
+
+    /// Saves a single setting, so changing one setting doesn't rewrite the rest.
+    fn save(&self, persist: &SettingsManager, setting: Setting) -> Result<(), String> {
+        let value = match setting {
+            Setting::Interval => self.interval.as_secs().to_string(),
+            Setting::Repeat => self.repeat.to_string(),
+            Setting::Shuffle => self.shuffle.to_string(),
+            Setting::PauseOnInteraction => self.pause_on_interaction.to_string(),
+            Setting::WaitForAnimation => self.wait_for_animation.to_string(),
+            Setting::Order => self.order.as_ref().to_owned(),
+            Setting::RatingWeights => {
+                let weights: Vec<_> = self.rating_weights.iter().map(u32::to_string).collect();
+                weights.join(",")
+            }
+            Setting::Cooldown => self.cooldown.to_string(),
+        };
+        persist.set(setting.key(), &value)
+    }
+}
+
+/// A slideshow setting which is saved under its own key.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Setting {
+    Interval,
+    Repeat,
+    Shuffle,
+    PauseOnInteraction,
+    WaitForAnimation,
+    Order,
+    RatingWeights,
+    Cooldown,
+}
+
+impl Setting {
+    fn key(self) -> &'static str {
+        match self {
+            Setting::Interval => INTERVAL_KEY,
+            Setting::Repeat => REPEAT_KEY,
+            Setting::Shuffle => SHUFFLE_KEY,
+            Setting::PauseOnInteraction => PAUSE_ON_INTERACTION_KEY,
+            Setting::WaitForAnimation => WAIT_FOR_ANIMATION_KEY,
+            Setting::Order => ORDER_KEY,
+            Setting::RatingWeights => RATING_WEIGHTS_KEY,
+            Setting::Cooldown => COOLDOWN_KEY,
+        }
+    }
+}
+
+fn parse_weights(weights: &str) -> Option<[u32; 6]> {
+    let parsed: Vec<_> = weights
+        .split(',')
+        .map(|w| w.trim().parse::<u32>().ok())
+        .collect::<Option<_>>()?;
+
+    let mut result = [0; 6];
+    if parsed.len() != result.len() {
+        return None;
+    }
+    result.copy_from_slice(&parsed);
+    Some(result)
+}
+
+impl SlideshowSettings {
+    pub fn update(&mut self, events: &EventSystem, persist: Option<&SettingsManager>) {
+        use crate::systems::events::*;
+
+        // Dialers send a change every frame while dragged, so only the changed settings are saved.
+        let mut changed = Vec::new();
+        for event in events.events() {
+            if let AppEvent::Slideshow(slideshow) = event {
+                let setting = match slideshow {
+                    Slideshow::Interval(interval) => {
+                        self.interval = *interval;
+                        Some(Setting::Interval)
+                    }
+                    Slideshow::Repeat(repeat) => {
+                        self.repeat = *repeat;
+                        Some(Setting::Repeat)
+                    }
+                    Slideshow::Shuffle(shuffle) => {
+                        self.shuffle = *shuffle;
+                        Some(Setting::Shuffle)
+                    }
+                    Slideshow::PauseOnInteraction(pause) => {
+                        self.pause_on_interaction = *pause;
+                        Some(Setting::PauseOnInteraction)
+                    }
+                    Slideshow::WaitForAnimation(wait) => {
+                        self.wait_for_animation = *wait;
+                        Some(Setting::WaitForAnimation)
+                    }
+                    Slideshow::Order(order) => {
+                        self.order = *order;
+                        Some(Setting::Order)
+                    }
+                    Slideshow::RatingWeight(rating, weight) => {
+                        self.rating_weights[weight_index(rating)] = *weight;
+                        Some(Setting::RatingWeights)
+                    }
+                    Slideshow::Cooldown(cooldown) => {
+                        self.cooldown = *cooldown;
+                        Some(Setting::Cooldown)
+                    }
+                    Slideshow::Start | Slideshow::Stop => None,
+                };
+                if let Some(setting) = setting.filter(|s| !changed.contains(s)) {
+                    changed.push(setting);
+                }
+            }
+        }
+
+        if let Some(persist) = persist {
+            for setting in changed {
+                self.save(persist, setting).log_err();
+            }
+        }
+    }
+
+    /// The relative chance of picking a file with this rating.
+    pub fn weight(&self, rating: &Option<Rating>) -> u32 {
+        self.rating_weights[weight_index(rating)]
+    }
+}
+
+fn weight_index(rating: &Option<Rating>) -> usize {
+    rating.clone().map_or(0, Into::into)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn parse_weights_requires_six_numbers() {
+        assert_eq!(parse_weights("0,1,2,3,4,5"), Some([0, 1, 2, 3, 4, 5]));
+        assert_eq!(parse_weights(" 1, 1,2 ,4,8,16"), Some([1, 1, 2, 4, 8, 16]));
+        assert_eq!(parse_weights("1,2,3,4,5"), None);
+        assert_eq!(parse_weights("1,2,3,4,5,6,7"), None);
+        assert_eq!(parse_weights("1,2,3,4,5,-6"), None);
+    }
+}
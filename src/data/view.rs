@@ -0,0 +1,437 @@
+use std::convert::AsRef;
+use std::fmt;
+use std::time::Duration;
+
+use super::persist::SettingsManager;
+use crate::support::LogError;
+use crate::systems::EventSystem;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ScaleMode {
+    FitAll,
+    ActualSize,
+    FitWidth,
+    FitHeight,
+    Fill,
+}
+
+pub static SCALE_MODES: &[ScaleMode] = &[
+    ScaleMode::FitAll,
+    ScaleMode::ActualSize,
+    ScaleMode::FitWidth,
+    ScaleMode::FitHeight,
+    ScaleMode::Fill,
+];
+
+impl fmt::Display for ScaleMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_ref())
+    }
+}
+
+impl AsRef<str> for ScaleMode {
+    fn as_ref(&self) -> &str {
+        match self {
+            ScaleMode::FitAll => "Fit All",
+            ScaleMode::ActualSize => "Actual Pixels",
+            ScaleMode::FitWidth => "Fit Width",
+            ScaleMode::FitHeight => "Fit Height",
+            ScaleMode::Fill => "Fill",
+        }
+    }
+}
+
+/// What is drawn behind the image.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Background {
+    /// A checkerboard behind the image so transparent areas are visible.
+    Checkerboard,
+    Black,
+    White,
+    /// The color in `ViewSettings::background_color`.
+    Custom,
+}
+
+pub static BACKGROUNDS: &[Background] = &[
+    Background::Checkerboard,
+    Background::Black,
+    Background::White,
+    Background::Custom,
+];
+
+impl fmt::Display for Background {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_ref())
+    }
+}
+
+impl AsRef<str> for Background {
+    fn as_ref(&self) -> &str {
+        match self {
+            Background::Checkerboard => "Checkerboard",
+            Background::Black => "Black",
+            Background::White => "White",
+            Background::Custom => "Custom Color",
+        }
+    }
+}
+
+/// How files are arranged in the viewer.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Layout {
+    Single,
+    /// The current and next file side by side, like the pages of a book.
+    Spread,
+    /// Every file stacked vertically and fitted to the width, for long strips.
+    Continuous,
+}
+
+pub static LAYOUTS: &[Layout] = &[Layout::Single, Layout::Spread, Layout::Continuous];
+
+impl fmt::Display for Layout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_ref())
+    }
+}
+
+impl AsRef<str> for Layout {
+    fn as_ref(&self) -> &str {
+        match self {
+            Layout::Single => "Single Page",
+            Layout::Spread => "Two Page Spread",
+            Layout::Continuous => "Continuous Scroll",
+        }
+    }
+}
+
+/// How the viewer animates from one image to the next.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Transition {
+    None,
+    /// The new image fades in over the old one.
+    Crossfade,
+    /// The new image pushes the old one out to the left.
+    Slide,
+    /// The old image fades out to black before the new one fades in.
+    FadeThroughBlack,
+}
+
+pub static TRANSITIONS: &[Transition] = &[
+    Transition::None,
+    Transition::Crossfade,
+    Transition::Slide,
+    Transition::FadeThroughBlack,
+];
+
+impl fmt::Display for Transition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_ref())
+    }
+}
+
+impl AsRef<str> for Transition {
+    fn as_ref(&self) -> &str {
+        match self {
+            Transition::None => "No Transition",
+            Transition::Crossfade => "Crossfade",
+            Transition::Slide => "Slide",
+            Transition::FadeThroughBlack => "Fade Through Black",
+        }
+    }
+}
+
+/// A zoom and position which can be restored on an image of any size.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SavedView {
+    pub scale: f64,
+    /// The horizontal position of the view's center as a fraction of the image width.
+    pub center_x: f64,
+    /// The vertical position of the view's center as a fraction of the image height.
+    pub center_y: f64,
+}
+
+/// Settings which control how images are presented by the viewer.
+///
+/// Everything except `lock_view` is saved as a user preference.
+#[derive(Debug, Clone)]
+pub struct ViewSettings {
+    /// The mode applied to each newly loaded image.
+    pub scale_mode: ScaleMode,
+    /// Whether `ScaleMode::FitAll` may scale images beyond their actual size.
+    pub upscale: bool,
+    /// Keep the zoom and position when changing images instead of applying `scale_mode`.
+    pub lock_view: bool,
+    /// Save the zoom and position of each image and restore it when the image is shown again.
+    pub remember_view: bool,
+    /// Outline each pixel once zoomed in to `pixel_grid_scale` or further.
+    pub pixel_grid: bool,
+    pub pixel_grid_scale: f64,
+    pub background: Background,
+    /// The RGB color used by `Background::Custom`.
+    pub background_color: [u8; 3],
+    pub layout: Layout,
+    /// Show spreads from right to left for manga.
+    pub right_to_left: bool,
+    /// Show the first file alone in a spread so a cover doesn't shift the pages.
+    pub single_first_page: bool,
+    /// Only used by the single page layout.
+    pub transition: Transition,
+    pub transition_duration: Duration,
+}
+
+impl Default for ViewSettings {
+    fn default() -> Self {
+        ViewSettings {
+            scale_mode: ScaleMode::FitAll,
+            upscale: true,
+            lock_view: false,
+            remember_view: false,
+            pixel_grid: true,
+            pixel_grid_scale: 8.0,
+            background: Background::Custom,
+            // The theme's background color
+            background_color: [46, 52, 54],
+            layout: Layout::Single,
+            right_to_left: false,
+            single_first_page: true,
+            transition: Transition::None,
+            transition_duration: Duration::from_millis(500),
+        }
+    }
+}
+
+const SCALE_MODE_KEY: &str = "view.scale_mode";
+const UPSCALE_KEY: &str = "view.upscale";
+const REMEMBER_VIEW_KEY: &str = "view.remember_view";
+const PIXEL_GRID_KEY: &str = "view.pixel_grid";
+const PIXEL_GRID_SCALE_KEY: &str = "view.pixel_grid_scale";
+const BACKGROUND_KEY: &str = "view.background";
+const BACKGROUND_COLOR_KEY: &str = "view.background_color";
+const LAYOUT_KEY: &str = "view.layout";
+const RIGHT_TO_LEFT_KEY: &str = "view.right_to_left";
+const SINGLE_FIRST_PAGE_KEY: &str = "view.single_first_page";
+const TRANSITION_KEY: &str = "view.transition";
+const TRANSITION_DURATION_KEY: &str = "view.transition_duration";
+
+// persistence
+impl ViewSettings {
+    pub fn load(persist: &SettingsManager) -> Self {
+        let mut settings = ViewSettings::default();
+
+        let get = |key| persist.get(key).log_err().and_then(|value| value);
+        if let Some(mode) = get(SCALE_MODE_KEY).and_then(|l| find_by_label(SCALE_MODES, &l)) {
+            settings.scale_mode = mode;
+        }
+        if let Some(bg) = get(BACKGROUND_KEY).and_then(|l| find_by_label(BACKGROUNDS, &l)) {
+            settings.background = bg;
+        }
+        if let Some(color) = get(BACKGROUND_COLOR_KEY).and_then(|c| parse_color(&c)) {
+            settings.background_color = color;
+        }
+        if let Some(layout) = get(LAYOUT_KEY).and_then(|l| find_by_label(LAYOUTS, &l)) {
+            settings.layout = layout;
+        }
+        if let Some(transition) = get(TRANSITION_KEY).and_then(|l| find_by_label(TRANSITIONS, &l)) {
+            settings.transition = transition;
+        }
+        if let Some(millis) = persist.get_parsed(TRANSITION_DURATION_KEY) {
+            settings.transition_duration = Duration::from_millis(millis);
+        }
+
+        settings.upscale = persist.get_parsed(UPSCALE_KEY).unwrap_or(settings.upscale);
+        settings.remember_view = persist
+            .get_parsed(REMEMBER_VIEW_KEY)
+            .unwrap_or(settings.remember_view);
+        settings.pixel_grid = persist
+            .get_parsed(PIXEL_GRID_KEY)
+            .unwrap_or(settings.pixel_grid);
+        settings.pixel_grid_scale = persist
+            .get_parsed(PIXEL_GRID_SCALE_KEY)
+            .unwrap_or(settings.pixel_grid_scale);
+        settings.right_to_left = persist
+            .get_parsed(RIGHT_TO_LEFT_KEY)
+            .unwrap_or(settings.right_to_left);
+        settings.single_first_page = persist
+            .get_parsed(SINGLE_FIRST_PAGE_KEY)
+            .unwrap_or(settings.single_first_page);
+
+        settings
+    }
+
+    /// Saves a single setting, so changing one setting doesn't rewrite the rest.
+    fn save(&self, persist: &SettingsManager, setting: Setting) -> Result<(), String> {
+        let value = match setting {
+            Setting::ScaleMode => self.scale_mode.as_ref().to_owned(),
+            Setting::Upscale => self.upscale.to_string(),
+            Setting::RememberView => self.remember_view.to_string(),
+            Setting::PixelGrid => self.pixel_grid.to_string(),
+            Setting::PixelGridScale => self.pixel_grid_scale.to_string(),
+            Setting::Background => self.background.as_ref().to_owned(),
+            Setting::BackgroundColor => {
+                let [r, g, b] = self.background_color;
+                format!("{},{},{}", r, g, b)
+            }
+            Setting::Layout => self.layout.as_ref().to_owned(),
+            Setting::RightToLeft => self.right_to_left.to_string(),
+            Setting::SingleFirstPage => self.single_first_page.to_string(),
+            Setting::Transition => self.transition.as_ref().to_owned(),
+            Setting::TransitionDuration => self.transition_duration.as_millis().to_string(),
+        };
+        persist.set(setting.key(), &value)
+    }
+}
+
+/// A view setting which is saved under its own key.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Setting {
+    ScaleMode,
+    Upscale,
+    RememberView,
+    PixelGrid,
+    PixelGridScale,
+    Background,
+    BackgroundColor,
+    Layout,
+    RightToLeft,
+    SingleFirstPage,
+    Transition,
+    TransitionDuration,
+}
+
+impl Setting {
+    fn key(self) -> &'static str {
+        match self {
+            Setting::ScaleMode => SCALE_MODE_KEY,
+            Setting::Upscale => UPSCALE_KEY,
+            Setting::RememberView => REMEMBER_VIEW_KEY,
+            Setting::PixelGrid => PIXEL_GRID_KEY,
+            Setting::PixelGridScale => PIXEL_GRID_SCALE_KEY,
+            Setting::Background => BACKGROUND_KEY,
+            Setting::BackgroundColor => BACKGROUND_COLOR_KEY,
+            Setting::Layout => LAYOUT_KEY,
+            Setting::RightToLeft => RIGHT_TO_LEFT_KEY,
+            Setting::SingleFirstPage => SINGLE_FIRST_PAGE_KEY,
+            Setting::Transition => TRANSITION_KEY,
+            Setting::TransitionDuration => TRANSITION_DURATION_KEY,
+        }
+    }
+}
+
+pub(super) fn find_by_label<T: AsRef<str> + Copy>(items: &[T], label: &str) -> Option<T> {
+    items.iter().find(|item| item.as_ref() == label).cloned()
+}
+
+fn parse_color(color: &str) -> Option<[u8; 3]> {
+    let mut parts = color.split(',').map(|p| p.trim().parse::<u8>().ok());
+    match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(Some(r)), Some(Some(g)), Some(Some(b)), None) => Some([r, g, b]),
+        _ => None,
+    }
+}
+
+impl ViewSettings {
+    pub fn update(&mut self, events: &EventSystem, persist: Option<&SettingsManager>) {
+        use crate::systems::events::*;
+
+        // Sliders send a change every frame while dragged, so only the changed settings are saved.
+        let mut changed = Vec::new();
+        for event in events.events() {
+            if let AppEvent::View(view) = event {
+                let setting = match view {
+                    View::ScaleMode(mode) => {
+                        self.scale_mode = *mode;
+                        Some(Setting::ScaleMode)
+                    }
+                    View::Upscale(upscale) => {
+                        self.upscale = *upscale;
+                        Some(Setting::Upscale)
+                    }
+                    View::Lock(lock) => {
+                        self.lock_view = *lock;
+                        None
+                    }
+                    View::Remember(remember) => {
+                        self.remember_view = *remember;
+                        Some(Setting::RememberView)
+                    }
+                    View::PixelGrid(grid) => {
+                        self.pixel_grid = *grid;
+                        Some(Setting::PixelGrid)
+                    }
+                    View::PixelGridScale(scale) => {
+                        self.pixel_grid_scale = *scale;
+                        Some(Setting::PixelGridScale)
+                    }
+                    View::Background(background) => {
+                        self.background = *background;
+                        Some(Setting::Background)
+                    }
+                    View::BackgroundColor(color) => {
+                        self.background_color = *color;
+                        Some(Setting::BackgroundColor)
+                    }
+                    View::Layout(layout) => {
+                        self.layout = *layout;
+                        Some(Setting::Layout)
+                    }
+                    View::RightToLeft(right_to_left) => {
+                        self.right_to_left = *right_to_left;
+                        Some(Setting::RightToLeft)
+                    }
+                    View::SingleFirstPage(single) => {
+                        self.single_first_page = *single;
+                        Some(Setting::SingleFirstPage)
+                    }
+                    View::Transition(transition) => {
+                        self.transition = *transition;
+                        Some(Setting::Transition)
+                    }
+                    View::TransitionDuration(duration) => {
+                        self.transition_duration = *duration;
+                        Some(Setting::TransitionDuration)
+                    }
+                    _ => None,
+                };
+                if let Some(setting) = setting.filter(|s| !changed.contains(s)) {
+                    changed.push(setting);
+                }
+            }
+        }
+
+        if let Some(persist) = persist {
+            for setting in changed {
+                self.save(persist, setting).log_err();
+            }
+        }
+    }
+
+    /// The layout after this one, used to cycle through them.
+    pub fn next_layout(&self) -> Layout {
+        let i = LAYOUTS.iter().position(|&l| l == self.layout).unwrap_or(0);
+        LAYOUTS[(i + 1) % LAYOUTS.len()]
+    }
+
+    /// The background after this one, used to cycle through them.
+    pub fn next_background(&self) -> Background {
+        let i = BACKGROUNDS
+            .iter()
+            .position(|&b| b == self.background)
+            .unwrap_or(0);
+        BACKGROUNDS[(i + 1) % BACKGROUNDS.len()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn parse_color_requires_three_bytes() {
+        assert_eq!(parse_color("1,2,3"), Some([1, 2, 3]));
+        assert_eq!(parse_color(" 255, 0 ,10 "), Some([255, 0, 10]));
+        assert_eq!(parse_color("1,2"), None);
+        assert_eq!(parse_color("1,2,3,4"), None);
+        assert_eq!(parse_color("1,2,256"), None);
+        assert_eq!(parse_color("red"), None);
+    }
+}
@@ -2,9 +2,14 @@ use conrod_core::image::Id;
 
 use crate::systems::ImageSystem;
 
+/// The size of the checkerboard texture, a multiple of `CHECKERBOARD_SQUARE`.
+pub const CHECKERBOARD_SIZE: u32 = 256;
+const CHECKERBOARD_SQUARE: u32 = 16;
+
 pub struct ImageIds {
     pub star_outline: Id,
     pub star_filled: Id,
+    pub checkerboard: Id,
 }
 
 pub struct Resources {
@@ -19,8 +24,17 @@ impl Resources {
             star_outline: image_sys.load_resource_image(include_bytes!(
                 "images/baseline_star_border_white_48dp.png"
             ))?,
+            checkerboard: image_sys.load_resource_rgba(checkerboard())?,
         };
 
         Ok(Resources { images })
     }
 }
+
+fn checkerboard() -> image::RgbaImage {
+    image::RgbaImage::from_fn(CHECKERBOARD_SIZE, CHECKERBOARD_SIZE, |x, y| {
+        let is_dark = (x / CHECKERBOARD_SQUARE + y / CHECKERBOARD_SQUARE) & 1 == 0;
+        let v = if is_dark { 0xcc } else { 0xff };
+        image::Rgba([v, v, v, 0xff])
+    })
+}
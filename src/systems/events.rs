@@ -1,15 +1,32 @@
-use crate::data::{File, FileSort, Rating};
+use crate::data::{
+    Background, File, FileSort, ImageInfo, Layout, NameOptions, Rating, RatingFilter, SavedView,
+    ScaleMode, SlideshowOrder, Transition,
+};
 use std::convert::Into;
+use std::path::PathBuf;
 
 #[derive(Debug, Clone)]
 pub enum AppEvent {
     Image(Image),
     Load(File),
+    /// Loads the file shown beside the current one in a two page spread.
+    LoadCompanion(Option<File>),
+    LoadThumbnail(PathBuf),
+    /// Loads a full size image for the continuous layout.
+    LoadPage(PathBuf),
+    /// Unloads every page except these.
+    RetainPages(Vec<PathBuf>),
+    /// Reads the image info of these files in the background.
+    LoadImageInfo(Vec<PathBuf>),
+    /// The image info read since the last frame, or why it couldn't be.
+    ImageInfoRead(Vec<(PathBuf, Result<ImageInfo, String>)>),
     Nav(Nav),
     Sort(FileSort),
     Filter(Filter),
     SetMeta(SetMeta),
     Slideshow(Slideshow),
+    View(View),
+    Window(Window),
 }
 
 #[derive(Debug, Clone)]
@@ -19,8 +36,25 @@ pub enum Image {
         w: u32,
         h: u32,
         file: File,
+        /// Whether the image has more than one frame.
+        is_animated: bool,
     },
     SwapImageId(conrod_core::image::Id),
+    CompanionLoaded {
+        id: conrod_core::image::Id,
+        w: u32,
+        h: u32,
+    },
+    CompanionUnloaded,
+    /// The current animated image has shown its last frame and is starting over.
+    LoopCompleted,
+    /// The previous image, kept while the new one animates in.
+    Transition {
+        outgoing: conrod_core::image::Id,
+        /// How far through the transition it is, from 0 to 1.
+        progress: f64,
+    },
+    TransitionFinished,
 }
 
 impl Into<AppEvent> for Image {
@@ -45,7 +79,19 @@ impl Into<AppEvent> for Nav {
 #[derive(Debug, Clone)]
 pub enum Filter {
     Text(String),
-    Rating(Option<Rating>),
+    NameOptions(NameOptions),
+    Rating(Option<RatingFilter>),
+    /// Replaces the whole filter, such as with a saved one.
+    Apply(crate::data::Filter),
+    /// Saves the current filter, for every directory if `is_global`.
+    Save {
+        name: String,
+        is_global: bool,
+    },
+    Delete {
+        name: String,
+        is_global: bool,
+    },
 }
 
 impl Into<AppEvent> for Filter {
@@ -57,6 +103,7 @@ impl Into<AppEvent> for Filter {
 #[derive(Debug, Clone)]
 pub enum SetMeta {
     Rating(Option<Rating>),
+    View(PathBuf, Option<SavedView>),
 }
 
 impl Into<AppEvent> for SetMeta {
@@ -67,8 +114,19 @@ impl Into<AppEvent> for SetMeta {
 
 #[derive(Debug, Clone)]
 pub enum Slideshow {
-    Start(std::time::Duration),
+    Start,
     Stop,
+    Interval(std::time::Duration),
+    /// Start over from the first file instead of stopping after the last one.
+    Repeat(bool),
+    /// Shuffle the files each time the slideshow starts over.
+    Shuffle(bool),
+    PauseOnInteraction(bool),
+    WaitForAnimation(bool),
+    Order(SlideshowOrder),
+    /// Sets the weight of files with a rating, or of unrated files.
+    RatingWeight(Option<Rating>, u32),
+    Cooldown(usize),
 }
 
 impl Into<AppEvent> for Slideshow {
@@ -76,3 +134,58 @@ impl Into<AppEvent> for Slideshow {
         AppEvent::Slideshow(self)
     }
 }
+
+#[derive(Debug, Clone)]
+pub enum View {
+    ScaleMode(ScaleMode),
+    Upscale(bool),
+    Lock(bool),
+    Remember(bool),
+    PixelGrid(bool),
+    PixelGridScale(f64),
+    Background(Background),
+    BackgroundColor([u8; 3]),
+    Layout(Layout),
+    RightToLeft(bool),
+    SingleFirstPage(bool),
+    Transition(Transition),
+    TransitionDuration(std::time::Duration),
+    ZoomIn,
+    ZoomOut,
+    /// Returns to the current scale mode.
+    ZoomReset,
+    Pan(Pan),
+}
+
+impl Into<AppEvent> for View {
+    fn into(self) -> AppEvent {
+        AppEvent::View(self)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Window {
+    Fullscreen(bool),
+    ToggleFullscreen,
+    Overlay(bool),
+    /// Sent when the user stops or resumes interacting while fullscreen.
+    Idle(bool),
+    /// Sent when the window is about to close, so anything unsaved can be saved first.
+    Close,
+}
+
+impl Into<AppEvent> for Window {
+    fn into(self) -> AppEvent {
+        AppEvent::Window(self)
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Pan {
+    Left,
+    Right,
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+}
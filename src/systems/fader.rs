@@ -0,0 +1,119 @@
+use glium::framebuffer::SimpleFrameBuffer;
+use glium::index::{NoIndices, PrimitiveType};
+use glium::texture::SrgbTexture2d;
+use glium::{program, uniform, Display, Program, Surface, VertexBuffer};
+
+use crate::support::ErrToString;
+
+#[derive(Copy, Clone)]
+struct Vertex {
+    position: [f32; 2],
+    tex_coords: [f32; 2],
+}
+
+// The macro uses `mem::uninitialized` internally.
+#[allow(deprecated)]
+mod vertex {
+    use super::Vertex;
+    use glium::implement_vertex;
+
+    implement_vertex!(Vertex, position, tex_coords);
+}
+
+/// Copies a texture into another with its alpha scaled, which conrod can't do when drawing images.
+pub struct Fader {
+    program: Program,
+    vertices: VertexBuffer<Vertex>,
+}
+
+impl Fader {
+    pub fn new(display: &Display) -> Result<Self, String> {
+        let program = program!(display,
+            140 => {
+                vertex: "
+                    #version 140
+                    in vec2 position;
+                    in vec2 tex_coords;
+                    out vec2 v_tex_coords;
+                    void main() {
+                        v_tex_coords = tex_coords;
+                        gl_Position = vec4(position, 0.0, 1.0);
+                    }
+                ",
+                fragment: "
+                    #version 140
+                    uniform sampler2D tex;
+                    uniform float alpha;
+                    in vec2 v_tex_coords;
+                    out vec4 f_color;
+                    void main() {
+                        vec4 color = texture(tex, v_tex_coords);
+                        f_color = vec4(color.rgb, color.a * alpha);
+                    }
+                ",
+            },
+            110 => {
+                vertex: "
+                    #version 110
+                    attribute vec2 position;
+                    attribute vec2 tex_coords;
+                    varying vec2 v_tex_coords;
+                    void main() {
+                        v_tex_coords = tex_coords;
+                        gl_Position = vec4(position, 0.0, 1.0);
+                    }
+                ",
+                fragment: "
+                    #version 110
+                    uniform sampler2D tex;
+                    uniform float alpha;
+                    varying vec2 v_tex_coords;
+                    void main() {
+                        vec4 color = texture2D(tex, v_tex_coords);
+                        gl_FragColor = vec4(color.rgb, color.a * alpha);
+                    }
+                ",
+            },
+        )
+        .err_to_string()?;
+
+        let corner = |x: f32, y: f32| Vertex {
+            position: [x * 2.0 - 1.0, y * 2.0 - 1.0],
+            tex_coords: [x, y],
+        };
+        let quad = [
+            corner(0.0, 0.0),
+            corner(1.0, 0.0),
+            corner(0.0, 1.0),
+            corner(1.0, 1.0),
+        ];
+        let vertices = VertexBuffer::new(display, &quad).err_to_string()?;
+
+        Ok(Fader { program, vertices })
+    }
+
+    /// Draws `source` over all of `target`, replacing its contents.
+    pub fn fade(
+        &self,
+        display: &Display,
+        source: &SrgbTexture2d,
+        target: &SrgbTexture2d,
+        alpha: f32,
+    ) -> Result<(), String> {
+        let mut frame = SimpleFrameBuffer::new(display, target).err_to_string()?;
+        frame.clear_color(0.0, 0.0, 0.0, 0.0);
+        let uniforms = uniform! {
+            tex: source,
+            alpha: alpha,
+        };
+        frame
+            .draw(
+                &self.vertices,
+                NoIndices(PrimitiveType::TriangleStrip),
+                &self.program,
+                &uniforms,
+                &Default::default(),
+            )
+            .err_to_string()
+    }
+}
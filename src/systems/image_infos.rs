@@ -0,0 +1,48 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, Sender};
+
+use super::thumbnails::{spawn_decoders, Decoded};
+use crate::data::ImageInfo;
+
+/// Reads image headers on background threads for the filters which need them.
+pub struct ImageInfos {
+    /// The paths sent to be read which haven't come back yet.
+    pending: HashSet<PathBuf>,
+    requests: Sender<PathBuf>,
+    results: Receiver<Decoded<ImageInfo>>,
+}
+
+impl ImageInfos {
+    pub fn new() -> Self {
+        let (requests, results) = spawn_decoders("image-info", ImageInfo::read);
+
+        ImageInfos {
+            pending: HashSet::new(),
+            requests,
+            results,
+        }
+    }
+
+    /// Queues the image for reading if it isn't already being read.
+    pub fn request(&mut self, path: &Path) {
+        if self.pending.contains(path) {
+            return;
+        }
+
+        if let Err(e) = self.requests.send(path.to_path_buf()) {
+            log::error!("Image info workers are not running: {}", e);
+            return;
+        }
+        self.pending.insert(path.to_path_buf());
+    }
+
+    /// Takes all image info read since the last call.
+    pub(super) fn receive(&mut self) -> Vec<Decoded<ImageInfo>> {
+        let results: Vec<_> = self.results.try_iter().collect();
+        for (path, _) in &results {
+            self.pending.remove(path);
+        }
+        results
+    }
+}
@@ -1,11 +1,12 @@
 use conrod_core::image::{Id, Map};
 use glium::texture::{RawImage2d, SrgbTexture2d};
 use glium::Display;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
 
-use super::{events as e, AppEvent, EventSystem};
-use crate::data::File;
+use super::fader::Fader;
+use super::{events as e, AppEvent, EventSystem, ImageInfos, Pages, Thumbnail, Thumbnails};
+use crate::data::{File, Layout, Transition, ViewSettings};
 use crate::support::{ErrToString, ExtensionIs};
 
 #[derive(Debug, Copy, Clone)]
@@ -16,21 +17,47 @@ struct FrameData {
     delay: Duration,
 }
 
+/// The previous image, kept until the transition to the next one finishes.
+struct Outgoing {
+    frame: FrameData,
+    /// The original texture when `frame` is a faded copy of it.
+    source: Option<SrgbTexture2d>,
+    started: Instant,
+    duration: Duration,
+}
+
 pub struct ImageSystem<'a> {
     image_map: Map<SrgbTexture2d>,
     frames: Vec<FrameData>,
     current_frame: usize,
     last_update: Instant,
+    /// The image shown beside the current one in a two page spread.
+    companion: Option<(PathBuf, FrameData)>,
+    outgoing: Option<Outgoing>,
+    fader: Option<Fader>,
+    thumbnails: Thumbnails,
+    pages: Pages,
+    image_infos: ImageInfos,
     display: &'a Display,
 }
 
 impl<'a> ImageSystem<'a> {
     pub fn new(display: &'a Display) -> Self {
+        let fader = Fader::new(display)
+            .map_err(|e| log::error!("Could not create fader, crossfades are disabled: {}", e))
+            .ok();
+
         ImageSystem {
             image_map: conrod_core::image::Map::new(),
             frames: Vec::new(),
             current_frame: 0,
             last_update: Instant::now(),
+            companion: None,
+            outgoing: None,
+            fader,
+            thumbnails: Thumbnails::new(),
+            pages: Pages::new(),
+            image_infos: ImageInfos::new(),
             display,
         }
     }
@@ -39,6 +66,14 @@ impl<'a> ImageSystem<'a> {
         &self.image_map
     }
 
+    pub fn thumbnails(&self) -> &Thumbnails {
+        &self.thumbnails
+    }
+
+    pub fn pages(&self) -> &Pages {
+        &self.pages
+    }
+
     pub fn time_to_next_update(&self) -> Option<Duration> {
         if self.frames.len() < 2 {
             return None;
@@ -55,11 +90,38 @@ impl<'a> ImageSystem<'a> {
             .or_else(|| Some(Duration::new(0, 0)))
     }
 
-    pub fn update(&mut self, events: &mut EventSystem) -> Result<(), String> {
+    pub fn update(&mut self, events: &mut EventSystem, view: &ViewSettings) -> Result<(), String> {
         let new_events: Vec<_> = events
             .events()
             .filter_map(|event| match event {
-                AppEvent::Load(file) => self.load_file(&file),
+                AppEvent::Load(file) => self.load_file(file, view),
+                AppEvent::LoadCompanion(file) => self.load_companion(file),
+                AppEvent::LoadThumbnail(path) => {
+                    self.thumbnails.request(path);
+                    None
+                }
+                AppEvent::LoadPage(path) => {
+                    self.pages.request(path);
+                    None
+                }
+                AppEvent::LoadImageInfo(paths) => {
+                    for path in paths {
+                        self.image_infos.request(path);
+                    }
+                    None
+                }
+                AppEvent::RetainPages(paths) => {
+                    for id in self.pages.retain(paths) {
+                        self.image_map.remove(id);
+                    }
+                    None
+                }
+                AppEvent::View(e::View::Layout(layout)) if *layout != Layout::Continuous => {
+                    for id in self.pages.retain(&[]) {
+                        self.image_map.remove(id);
+                    }
+                    None
+                }
                 _ => None,
             })
             .collect();
@@ -67,6 +129,16 @@ impl<'a> ImageSystem<'a> {
             events.push(event);
         }
 
+        self.upload_thumbnails();
+        self.upload_pages();
+        let infos = self.image_infos.receive();
+        if !infos.is_empty() {
+            events.push(AppEvent::ImageInfoRead(infos));
+        }
+        if let Some(event) = self.update_transition() {
+            events.push(event);
+        }
+
         if self.frames.len() > 1 {
             let now = Instant::now();
 
@@ -78,6 +150,9 @@ impl<'a> ImageSystem<'a> {
                 self.last_update = now;
                 let cur = &self.frames[self.current_frame];
                 events.push(e::Image::SwapImageId(cur.id).into());
+                if self.current_frame == 0 {
+                    events.push(e::Image::LoopCompleted.into());
+                }
                 log::trace!(
                     "update image frame: {}, {:?} <= {:?}",
                     self.current_frame,
@@ -91,21 +166,93 @@ impl<'a> ImageSystem<'a> {
     }
 }
 
+// Transitions
+impl<'a> ImageSystem<'a> {
+    /// Keeps the frame being shown so the viewer can animate away from it.
+    fn start_transition(&mut self, frame: FrameData, view: &ViewSettings) {
+        let mut outgoing = Outgoing {
+            frame,
+            source: None,
+            started: Instant::now(),
+            duration: view.transition_duration,
+        };
+
+        if view.transition == Transition::Crossfade && self.fader.is_some() {
+            let faded = SrgbTexture2d::empty(self.display, frame.w, frame.h);
+            match faded {
+                Ok(faded) => {
+                    outgoing.source = self.image_map.remove(frame.id);
+                    outgoing.frame.id = self.image_map.insert(faded);
+                }
+                Err(e) => log::warn!("Could not create crossfade texture: {}", e),
+            }
+        }
+
+        self.outgoing = Some(outgoing);
+    }
+
+    fn finish_transition(&mut self) {
+        if let Some(outgoing) = self.outgoing.take() {
+            self.image_map.remove(outgoing.frame.id);
+        }
+    }
+
+    fn update_transition(&mut self) -> Option<AppEvent> {
+        let outgoing = self.outgoing.as_ref()?;
+        let elapsed = outgoing.started.elapsed();
+        if elapsed >= outgoing.duration {
+            self.finish_transition();
+            return Some(e::Image::TransitionFinished.into());
+        }
+
+        let progress = elapsed.as_secs_f64() / outgoing.duration.as_secs_f64();
+        if let (Some(source), Some(fader)) = (&outgoing.source, &self.fader) {
+            if let Some(target) = self.image_map.get(&outgoing.frame.id) {
+                fader
+                    .fade(self.display, source, target, (1.0 - progress) as f32)
+                    .unwrap_or_else(|e| log::warn!("Could not fade image: {}", e));
+            }
+        }
+
+        Some(
+            e::Image::Transition {
+                outgoing: outgoing.frame.id,
+                progress,
+            }
+            .into(),
+        )
+    }
+}
+
 // Image loading
 impl<'a> ImageSystem<'a> {
-    fn unload_image(&mut self) {
+    /// Removes every frame of the current image except `keep`.
+    fn unload_image(&mut self, keep: Option<Id>) {
         for frame in &self.frames {
-            self.image_map.remove(frame.id);
+            if Some(frame.id) != keep {
+                self.image_map.remove(frame.id);
+            }
         }
 
         self.frames = Vec::new();
         self.current_frame = 0;
     }
 
-    fn load_file(&mut self, file: &File) -> Option<AppEvent> {
+    fn load_file(&mut self, file: &File, view: &ViewSettings) -> Option<AppEvent> {
         let path = &file.path;
         log::info!("Loading image from path: {}", path.display());
-        self.unload_image();
+
+        self.finish_transition();
+        let has_transition = view.transition != Transition::None && view.layout == Layout::Single;
+        let outgoing = self
+            .frames
+            .get(self.current_frame)
+            .cloned()
+            .filter(|_| has_transition);
+        self.unload_image(outgoing.map(|frame| frame.id));
+        if let Some(frame) = outgoing {
+            self.start_transition(frame, view);
+        }
 
         if !path.exists() || !path.is_file() {
             log::error!(
@@ -129,6 +276,7 @@ impl<'a> ImageSystem<'a> {
                     w: frame.w,
                     h: frame.h,
                     file: file.clone(),
+                    is_animated: self.frames.len() > 1,
                 }
                 .into(),
             )
@@ -137,6 +285,55 @@ impl<'a> ImageSystem<'a> {
         }
     }
 
+    fn load_companion(&mut self, file: &Option<File>) -> Option<AppEvent> {
+        let file = match file {
+            Some(file) => file,
+            None => {
+                if let Some((_, frame)) = self.companion.take() {
+                    self.image_map.remove(frame.id);
+                }
+                return Some(e::Image::CompanionUnloaded.into());
+            }
+        };
+
+        let is_loaded = match &self.companion {
+            Some((path, _)) => *path == file.path,
+            None => false,
+        };
+        if !is_loaded {
+            log::info!("Loading companion from path: {}", file.path.display());
+            if let Some((_, frame)) = self.companion.take() {
+                self.image_map.remove(frame.id);
+            }
+
+            // Companions are always static, only the current image is animated.
+            match load_image_from_file(self.display, &file.path) {
+                Ok((texture, (w, h))) => {
+                    let frame = FrameData {
+                        id: self.image_map.insert(texture),
+                        w,
+                        h,
+                        delay: Duration::default(),
+                    };
+                    self.companion = Some((file.path.clone(), frame));
+                }
+                Err(e) => {
+                    log::error!("Could not load companion {}: {}", file.path.display(), e);
+                    return Some(e::Image::CompanionUnloaded.into());
+                }
+            }
+        }
+
+        self.companion.as_ref().map(|(_, frame)| {
+            e::Image::CompanionLoaded {
+                id: frame.id,
+                w: frame.w,
+                h: frame.h,
+            }
+            .into()
+        })
+    }
+
     fn load_gif(&mut self, path: &Path) -> Result<FrameData, String> {
         use gif::Decoder;
         use gif_dispose::{Screen, RGBA8};
@@ -203,11 +400,50 @@ impl<'a> ImageSystem<'a> {
         Ok(frame)
     }
 
+    fn upload_thumbnails(&mut self) {
+        for (path, result) in self.thumbnails.receive() {
+            match result.and_then(|image| texture_from_rgba(self.display, image)) {
+                Ok((texture, (w, h))) => {
+                    let id = self.image_map.insert(texture);
+                    for evicted in self.thumbnails.insert(path, Thumbnail { id, w, h }) {
+                        self.image_map.remove(evicted);
+                    }
+                }
+                Err(e) => {
+                    log::warn!("Could not load thumbnail for {}: {}", path.display(), e);
+                    self.thumbnails.fail(path);
+                }
+            }
+        }
+    }
+
+    fn upload_pages(&mut self) {
+        for (path, result) in self.pages.receive() {
+            match result.and_then(|image| texture_from_rgba(self.display, image)) {
+                Ok((texture, (w, h))) => {
+                    let id = self.image_map.insert(texture);
+                    if let Some(replaced) = self.pages.insert(path, Thumbnail { id, w, h }) {
+                        self.image_map.remove(replaced);
+                    }
+                }
+                Err(e) => {
+                    log::warn!("Could not load page {}: {}", path.display(), e);
+                    self.pages.fail(path);
+                }
+            }
+        }
+    }
+
     pub fn load_resource_image(&mut self, buffer: &[u8]) -> Result<Id, String> {
         let image = image::load_from_memory(buffer).err_to_string()?;
         let (texture, _) = texture_from_image(self.display, image)?;
         Ok(self.image_map.insert(texture))
     }
+
+    pub fn load_resource_rgba(&mut self, image: image::RgbaImage) -> Result<Id, String> {
+        let (texture, _) = texture_from_rgba(self.display, image)?;
+        Ok(self.image_map.insert(texture))
+    }
 }
 
 fn load_image_from_file(
@@ -222,7 +458,13 @@ fn texture_from_image(
     display: &Display,
     image: image::DynamicImage,
 ) -> Result<(SrgbTexture2d, (u32, u32)), String> {
-    let rgba = image.to_rgba();
+    texture_from_rgba(display, image.to_rgba())
+}
+
+fn texture_from_rgba(
+    display: &Display,
+    rgba: image::RgbaImage,
+) -> Result<(SrgbTexture2d, (u32, u32)), String> {
     let dimensions = rgba.dimensions();
     let raw = RawImage2d::from_raw_rgba_reversed(&rgba.into_raw(), dimensions);
     SrgbTexture2d::new(display, raw)
@@ -1,7 +1,18 @@
 mod event_system;
 pub mod events;
+mod fader;
+mod image_infos;
 mod image_system;
+mod pages;
+mod slideshow_system;
+mod thumbnails;
+mod window_system;
 
 pub use self::event_system::*;
 pub use self::events::AppEvent;
+pub use self::image_infos::*;
 pub use self::image_system::*;
+pub use self::pages::*;
+pub use self::slideshow_system::*;
+pub use self::thumbnails::*;
+pub use self::window_system::*;
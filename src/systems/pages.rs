@@ -0,0 +1,105 @@
+use conrod_core::image::Id;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, Sender};
+
+use super::thumbnails::{spawn_decoders, Decoded};
+use super::{Thumbnail, ThumbnailState};
+use crate::support::ErrToString;
+
+/// Pages are scaled down to fit within this size so they can always be uploaded as a texture.
+const MAX_PAGE_SIZE: [u32; 2] = [2048, 16384];
+
+/// Full size images for the continuous layout, decoded on background threads.
+///
+/// Pages stay loaded until the viewer stops retaining them.
+pub struct Pages {
+    states: HashMap<PathBuf, ThumbnailState>,
+    requests: Sender<PathBuf>,
+    results: Receiver<Decoded>,
+}
+
+impl Pages {
+    pub fn new() -> Self {
+        let (requests, results) = spawn_decoders("page", decode_page);
+
+        Pages {
+            states: HashMap::new(),
+            requests,
+            results,
+        }
+    }
+
+    pub fn get(&self, path: &Path) -> Option<ThumbnailState> {
+        self.states.get(path).cloned()
+    }
+
+    /// Queues the page for decoding if it hasn't been requested yet.
+    pub fn request(&mut self, path: &Path) {
+        if self.states.contains_key(path) {
+            return;
+        }
+
+        log::trace!("Requesting page: {}", path.display());
+        let state = match self.requests.send(path.to_path_buf()) {
+            Ok(_) => ThumbnailState::Loading,
+            Err(e) => {
+                log::error!("Page workers are not running: {}", e);
+                ThumbnailState::Failed
+            }
+        };
+        self.states.insert(path.to_path_buf(), state);
+    }
+
+    /// Forgets every page not in `keep` and returns the ids of those which were loaded.
+    ///
+    /// Pages which are still decoding are dropped when they arrive.
+    pub(super) fn retain(&mut self, keep: &[PathBuf]) -> Vec<Id> {
+        let mut unloaded = Vec::new();
+        self.states.retain(|path, state| {
+            let is_kept = keep.contains(path);
+            if let (false, ThumbnailState::Loaded(page)) = (is_kept, &state) {
+                unloaded.push(page.id);
+            }
+            is_kept
+        });
+        unloaded
+    }
+
+    /// Takes all images decoded since the last call which are still wanted.
+    ///
+    /// A page dropped and requested again while decoding is decoded twice, so only the first
+    /// result is taken.
+    pub(super) fn receive(&mut self) -> Vec<Decoded> {
+        let states = &self.states;
+        self.results
+            .try_iter()
+            .filter(|(path, _)| matches!(states.get(path), Some(ThumbnailState::Loading)))
+            .collect()
+    }
+
+    /// Stores a decoded page, returning the id of any page it replaced so it can be unloaded.
+    pub(super) fn insert(&mut self, path: PathBuf, page: Thumbnail) -> Option<Id> {
+        match self.states.insert(path, ThumbnailState::Loaded(page)) {
+            Some(ThumbnailState::Loaded(replaced)) => Some(replaced.id),
+            _ => None,
+        }
+    }
+
+    pub(super) fn fail(&mut self, path: PathBuf) {
+        self.states.insert(path, ThumbnailState::Failed);
+    }
+}
+
+fn decode_page(path: &Path) -> Result<image::RgbaImage, String> {
+    use image::GenericImageView;
+
+    let image = image::open(path).err_to_string()?;
+    let [max_w, max_h] = MAX_PAGE_SIZE;
+    let image = if image.width() > max_w || image.height() > max_h {
+        image.thumbnail(max_w, max_h)
+    } else {
+        image
+    };
+    Ok(image.to_rgba())
+}
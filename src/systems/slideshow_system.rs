@@ -0,0 +1,303 @@
+use rand::Rng;
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use super::{events as e, AppEvent, EventSystem};
+use crate::data::persist::SettingsManager;
+use crate::data::{FileList, FileSort, SlideshowOrder, SlideshowSettings, ViewSettings};
+
+/// How long after the last input the slideshow resumes when pausing on interaction.
+const INTERACTION_DELAY: Duration = Duration::from_secs(3);
+
+/// Advances through the files on a timer while the slideshow is running.
+pub struct SlideshowSystem {
+    settings: SlideshowSettings,
+    is_running: bool,
+    /// How long the current file has been shown, not counting time spent paused.
+    elapsed: Duration,
+    last_update: Instant,
+    last_activity: Option<Instant>,
+    is_animated: bool,
+    is_loop_completed: bool,
+    /// The files most recently shown by the slideshow, oldest first.
+    recent: VecDeque<PathBuf>,
+}
+
+impl SlideshowSystem {
+    pub fn new(settings: SlideshowSettings) -> Self {
+        SlideshowSystem {
+            settings,
+            is_running: false,
+            elapsed: Duration::default(),
+            last_update: Instant::now(),
+            last_activity: None,
+            is_animated: false,
+            is_loop_completed: false,
+            recent: VecDeque::new(),
+        }
+    }
+
+    pub fn settings(&self) -> &SlideshowSettings {
+        &self.settings
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.is_running
+    }
+
+    /// How long until the next file is shown, ignoring any pauses.
+    pub fn remaining(&self) -> Duration {
+        self.settings
+            .interval
+            .checked_sub(self.elapsed)
+            .unwrap_or_default()
+    }
+
+    /// How far through the interval the current file is, from 0 to 1.
+    pub fn progress(&self) -> f64 {
+        let interval = duration_secs(self.settings.interval);
+        if interval > 0.0 {
+            (duration_secs(self.elapsed) / interval).min(1.0)
+        } else {
+            1.0
+        }
+    }
+
+    /// Records user input, which holds the slideshow when pausing on interaction.
+    pub fn activity(&mut self) {
+        self.last_activity = Some(Instant::now());
+    }
+
+    fn is_paused(&self) -> bool {
+        self.settings.pause_on_interaction
+            && self
+                .last_activity
+                .is_some_and(|last| last.elapsed() < INTERACTION_DELAY)
+    }
+
+    pub fn update(
+        &mut self,
+        events: &mut EventSystem,
+        files: Option<&FileList>,
+        view: &ViewSettings,
+        persist: Option<&SettingsManager>,
+    ) {
+        self.settings.update(events, persist);
+
+        let mut is_stopped = false;
+        for event in events.events() {
+            match event {
+                AppEvent::Slideshow(e::Slideshow::Start) => {
+                    log::info!("Starting slideshow every {:?}", self.settings.interval);
+                    self.is_running = true;
+                    self.elapsed = Duration::default();
+                }
+                AppEvent::Slideshow(e::Slideshow::Stop) => {
+                    log::info!("Stopping slideshow");
+                    self.is_running = false;
+                }
+                AppEvent::Image(e::Image::Loaded { is_animated, .. }) => {
+                    self.elapsed = Duration::default();
+                    self.is_animated = *is_animated;
+                    self.is_loop_completed = false;
+                }
+                AppEvent::Image(e::Image::LoopCompleted) => self.is_loop_completed = true,
+                _ => (),
+            }
+            is_stopped |= matches!(event, AppEvent::Slideshow(e::Slideshow::Stop));
+        }
+
+        let now = Instant::now();
+        let delta = now.duration_since(self.last_update);
+        self.last_update = now;
+
+        let files = match files {
+            Some(files) if self.is_running && !is_stopped => files,
+            _ => return,
+        };
+
+        if !self.is_paused() {
+            self.elapsed = (self.elapsed + delta).min(self.settings.interval);
+        }
+
+        let is_waiting =
+            self.settings.wait_for_animation && self.is_animated && !self.is_loop_completed;
+        if self.elapsed >= self.settings.interval && !is_waiting && !self.is_paused() {
+            self.elapsed = Duration::default();
+            self.is_loop_completed = false;
+            if let Some(file) = files.current() {
+                self.recent.push_back(file.path.clone());
+            }
+            while self.recent.len() > self.settings.cooldown {
+                self.recent.pop_front();
+            }
+
+            let mut rng = rand::thread_rng();
+            events.push_all(advance(files, view, &self.settings, &self.recent, &mut rng));
+        }
+    }
+}
+
+/// The events which move the slideshow on from the current file.
+fn advance<R: Rng>(
+    files: &FileList,
+    view: &ViewSettings,
+    settings: &SlideshowSettings,
+    recent: &VecDeque<PathBuf>,
+    rng: &mut R,
+) -> Vec<AppEvent> {
+    if settings.order == SlideshowOrder::Weighted {
+        if let Some(i) = pick_weighted(files, settings, recent, rng) {
+            return vec![e::Nav::ImageIndex(i).into()];
+        }
+    }
+
+    if !files.is_last(view) {
+        vec![e::Nav::ImageNext.into()]
+    } else if !settings.repeat {
+        vec![e::Slideshow::Stop.into()]
+    } else if settings.shuffle {
+        vec![
+            AppEvent::Sort(FileSort::Random),
+            e::Nav::ImageIndex(0).into(),
+        ]
+    } else {
+        vec![e::Nav::ImageIndex(0).into()]
+    }
+}
+
+/// Picks a random file other than the current one, weighted by rating.
+///
+/// Recently shown files are skipped unless there is nothing else to show.
+fn pick_weighted<R: Rng>(
+    files: &FileList,
+    settings: &SlideshowSettings,
+    recent: &VecDeque<PathBuf>,
+    rng: &mut R,
+) -> Option<usize> {
+    use rand::seq::SliceRandom;
+
+    let candidates: Vec<_> = (0..files.len())
+        .filter(|&i| i != files.current_index())
+        .filter_map(|i| files.get_file(i).map(|file| (i, file)))
+        .filter(|(_, file)| settings.weight(&file.rating) > 0)
+        .collect();
+    let fresh: Vec<_> = candidates
+        .iter()
+        .filter(|(_, file)| !recent.contains(&file.path))
+        .cloned()
+        .collect();
+
+    let candidates = if fresh.is_empty() { candidates } else { fresh };
+    candidates
+        .choose_weighted(rng, |(_, file)| settings.weight(&file.rating))
+        .ok()
+        .map(|(i, _)| *i)
+}
+
+fn duration_secs(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + f64::from(duration.subsec_millis()) / 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{File, Rating};
+
+    #[test]
+    pub fn advance_starts_over_or_stops_at_end() {
+        let files = (1..=3)
+            .map(|i| File::from(&*format!("{}.png", i)))
+            .collect();
+        let mut list = FileList::from_files(files, None);
+        let view = ViewSettings::default();
+        let settings = SlideshowSettings::default();
+        let recent = VecDeque::new();
+        let mut rng = rand::thread_rng();
+
+        assert!(matches!(
+            advance(&list, &view, &settings, &recent, &mut rng).as_slice(),
+            [AppEvent::Nav(e::Nav::ImageNext)]
+        ));
+
+        let mut events = EventSystem::new();
+        events.push(e::Nav::ImageIndex(2).into());
+        events.update();
+        list.update(&mut events, &view, None);
+
+        assert!(
+            matches!(
+                advance(&list, &view, &settings, &recent, &mut rng).as_slice(),
+                [AppEvent::Nav(e::Nav::ImageIndex(0))]
+            ),
+            "repeat should go back to the start"
+        );
+        let settings = SlideshowSettings {
+            shuffle: true,
+            ..settings
+        };
+        assert!(
+            matches!(
+                advance(&list, &view, &settings, &recent, &mut rng).as_slice(),
+                [
+                    AppEvent::Sort(FileSort::Random),
+                    AppEvent::Nav(e::Nav::ImageIndex(0))
+                ]
+            ),
+            "shuffle should reshuffle before starting over"
+        );
+        let settings = SlideshowSettings {
+            repeat: false,
+            ..settings
+        };
+        assert!(
+            matches!(
+                advance(&list, &view, &settings, &recent, &mut rng).as_slice(),
+                [AppEvent::Slideshow(e::Slideshow::Stop)]
+            ),
+            "the slideshow should stop at the end"
+        );
+    }
+
+    #[test]
+    pub fn pick_weighted_skips_recent_and_unweighted_files() {
+        let files = vec![
+            ("current.png", Some(5)),
+            ("recent.png", Some(5)),
+            ("unrated.png", None),
+            ("fresh.png", Some(4)),
+        ]
+        .into_iter()
+        .map(|(path, rating)| File {
+            rating: rating.map(Rating::from),
+            ..File::from(path)
+        })
+        .collect();
+        let list = FileList::from_files(files, None);
+        let settings = SlideshowSettings {
+            rating_weights: [0, 1, 1, 1, 1, 1],
+            ..SlideshowSettings::default()
+        };
+        let mut rng = rand::thread_rng();
+        let index_of = |name: &str| {
+            (0..list.len())
+                .find(|&i| list.get_file(i).map(File::name) == Some(name.to_owned()))
+                .unwrap()
+        };
+
+        let mut recent = VecDeque::new();
+        recent.push_back(PathBuf::from("recent.png"));
+        for _ in 0..20 {
+            let picked = pick_weighted(&list, &settings, &recent, &mut rng);
+            assert_eq!(picked, Some(index_of("fresh.png")));
+        }
+
+        recent.push_back(PathBuf::from("fresh.png"));
+        let picked = pick_weighted(&list, &settings, &recent, &mut rng);
+        assert!(
+            picked == Some(index_of("recent.png")) || picked == Some(index_of("fresh.png")),
+            "recent files are shown when there is nothing else"
+        );
+    }
+}
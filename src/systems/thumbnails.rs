@@ -0,0 +1,145 @@
+use conrod_core::image::Id;
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::support::ErrToString;
+
+/// The longest edge of a generated thumbnail in pixels.
+pub const THUMBNAIL_SIZE: u32 = 128;
+
+/// Thumbnails are evicted oldest first once more than this many are loaded.
+const MAX_THUMBNAILS: usize = 500;
+const WORKER_COUNT: usize = 2;
+
+pub(super) type Decoded<T = image::RgbaImage> = (PathBuf, Result<T, String>);
+
+/// A decoded image which has been uploaded, also used for the pages of a continuous strip.
+#[derive(Debug, Copy, Clone)]
+pub struct Thumbnail {
+    pub id: Id,
+    pub w: u32,
+    pub h: u32,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum ThumbnailState {
+    Loading,
+    Loaded(Thumbnail),
+    Failed,
+}
+
+/// Tracks thumbnails which are decoded on background threads.
+///
+/// Decoded images are handed back to the `ImageSystem`, which owns the texture map
+/// and uploads them on the main thread.
+pub struct Thumbnails {
+    states: HashMap<PathBuf, ThumbnailState>,
+    loaded: VecDeque<PathBuf>,
+    requests: Sender<PathBuf>,
+    results: Receiver<Decoded>,
+}
+
+impl Thumbnails {
+    pub fn new() -> Self {
+        let (requests, results) = spawn_decoders("thumbnail", decode_thumbnail);
+
+        Thumbnails {
+            states: HashMap::new(),
+            loaded: VecDeque::new(),
+            requests,
+            results,
+        }
+    }
+
+    pub fn get(&self, path: &Path) -> Option<ThumbnailState> {
+        self.states.get(path).cloned()
+    }
+
+    /// Queues the thumbnail for decoding if it hasn't been requested yet.
+    pub fn request(&mut self, path: &Path) {
+        if self.states.contains_key(path) {
+            return;
+        }
+
+        log::trace!("Requesting thumbnail: {}", path.display());
+        let state = match self.requests.send(path.to_path_buf()) {
+            Ok(_) => ThumbnailState::Loading,
+            Err(e) => {
+                log::error!("Thumbnail workers are not running: {}", e);
+                ThumbnailState::Failed
+            }
+        };
+        self.states.insert(path.to_path_buf(), state);
+    }
+
+    /// Takes all images decoded since the last call.
+    pub(super) fn receive(&mut self) -> Vec<Decoded> {
+        self.results.try_iter().collect()
+    }
+
+    /// Stores a loaded thumbnail and returns the ids of any evicted thumbnails.
+    pub(super) fn insert(&mut self, path: PathBuf, thumbnail: Thumbnail) -> Vec<Id> {
+        self.states
+            .insert(path.clone(), ThumbnailState::Loaded(thumbnail));
+        self.loaded.push_back(path);
+
+        let mut evicted = Vec::new();
+        while self.loaded.len() > MAX_THUMBNAILS {
+            if let Some(path) = self.loaded.pop_front() {
+                if let Some(ThumbnailState::Loaded(t)) = self.states.remove(&path) {
+                    evicted.push(t.id);
+                }
+            }
+        }
+        evicted
+    }
+
+    pub(super) fn fail(&mut self, path: PathBuf) {
+        self.states.insert(path, ThumbnailState::Failed);
+    }
+}
+
+/// Starts threads which decode each requested path and send back the result.
+pub(super) fn spawn_decoders<T: Send + 'static>(
+    name: &str,
+    decode: fn(&Path) -> Result<T, String>,
+) -> (Sender<PathBuf>, Receiver<Decoded<T>>) {
+    let (requests, request_rx) = channel::<PathBuf>();
+    let (result_tx, results) = channel();
+    let request_rx = Arc::new(Mutex::new(request_rx));
+
+    for i in 0..WORKER_COUNT {
+        let request_rx = request_rx.clone();
+        let result_tx = result_tx.clone();
+        let spawned = thread::Builder::new()
+            .name(format!("{}-{}", name, i))
+            .spawn(move || loop {
+                let path = match request_rx.lock() {
+                    Ok(rx) => rx.recv(),
+                    Err(_) => return,
+                };
+                let path = match path {
+                    Ok(path) => path,
+                    Err(_) => return,
+                };
+
+                let result = decode(&path);
+                if result_tx.send((path, result)).is_err() {
+                    return;
+                }
+            });
+        if let Err(e) = spawned {
+            log::error!("Failed to start {} worker: {}", name, e);
+        }
+    }
+
+    (requests, results)
+}
+
+fn decode_thumbnail(path: &Path) -> Result<image::RgbaImage, String> {
+    let image = image::open(path).err_to_string()?;
+    Ok(image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE).to_rgba())
+}
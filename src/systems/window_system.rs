@@ -0,0 +1,99 @@
+use glium::glutin::dpi::{LogicalPosition, LogicalSize};
+use glium::Display;
+use std::time::{Duration, Instant};
+
+use super::{events as e, AppEvent, EventSystem};
+
+/// How long without any input before the cursor and overlay are hidden in fullscreen.
+const IDLE_DELAY: Duration = Duration::from_secs(3);
+
+/// Switches the window in and out of fullscreen and tracks whether the user is idle.
+pub struct WindowSystem {
+    is_fullscreen: bool,
+    /// The window's position and size from before it went fullscreen.
+    restore: Option<(LogicalPosition, LogicalSize)>,
+    last_activity: Instant,
+    is_idle: bool,
+}
+
+impl WindowSystem {
+    pub fn new() -> Self {
+        WindowSystem {
+            is_fullscreen: false,
+            restore: None,
+            last_activity: Instant::now(),
+            is_idle: false,
+        }
+    }
+
+    pub fn is_fullscreen(&self) -> bool {
+        self.is_fullscreen
+    }
+
+    /// The window's position and size, or what they will be restored to when fullscreen.
+    pub fn geometry(&self, display: &Display) -> Option<(LogicalPosition, LogicalSize)> {
+        if self.is_fullscreen {
+            return self.restore;
+        }
+
+        let window = display.gl_window();
+        match (window.get_position(), window.get_inner_size()) {
+            (Some(position), Some(size)) => Some((position, size)),
+            _ => None,
+        }
+    }
+
+    /// Records user input, which ends any idle period.
+    pub fn activity(&mut self) {
+        self.last_activity = Instant::now();
+    }
+
+    pub fn update(&mut self, display: &Display, events: &mut EventSystem) {
+        let mut fullscreen = None;
+        for event in events.events() {
+            match event {
+                AppEvent::Window(e::Window::Fullscreen(is_fullscreen)) => {
+                    fullscreen = Some(*is_fullscreen)
+                }
+                AppEvent::Window(e::Window::ToggleFullscreen) => {
+                    fullscreen = Some(!fullscreen.unwrap_or(self.is_fullscreen))
+                }
+                _ => (),
+            }
+        }
+
+        if let Some(fullscreen) = fullscreen {
+            self.set_fullscreen(display, fullscreen);
+        }
+
+        let is_idle = self.is_fullscreen && self.last_activity.elapsed() >= IDLE_DELAY;
+        if is_idle != self.is_idle {
+            log::debug!("Idle: {}", is_idle);
+            self.is_idle = is_idle;
+            display.gl_window().hide_cursor(is_idle);
+            events.push(e::Window::Idle(is_idle).into());
+        }
+    }
+
+    fn set_fullscreen(&mut self, display: &Display, fullscreen: bool) {
+        if fullscreen == self.is_fullscreen {
+            return;
+        }
+
+        log::info!("Fullscreen: {}", fullscreen);
+        let window = display.gl_window();
+        if fullscreen {
+            self.restore = self.geometry(display);
+            window.set_fullscreen(Some(window.get_current_monitor()));
+        } else {
+            window.set_fullscreen(None);
+            if let Some((position, size)) = self.restore.take() {
+                window.set_inner_size(size);
+                window.set_position(position);
+            }
+        }
+
+        self.is_fullscreen = fullscreen;
+        self.activity();
+    }
+}
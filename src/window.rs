@@ -4,12 +4,17 @@ use glium::Surface;
 use ttf_noto_sans;
 
 use crate::components::App;
+use crate::data::persist::SettingsManager;
+use crate::data::{FileList, Session, SlideshowSettings, ViewSettings, WindowGeometry};
 use crate::res::Resources;
 use crate::support::{EventLoop, GliumDisplayWinitWrapper, LogError};
 use crate::systems::{self, events as e};
 
 const INITIAL_WINDOW_WIDTH: u32 = 800;
 const INITIAL_WINDOW_HEIGHT: u32 = 500;
+/// Frames run after the window is asked to close: one for `Close` to reach the widgets and one
+/// for the events they send in return, such as the current image's view.
+const CLOSING_FRAMES: u32 = 2;
 
 widget_ids!(struct Ids {
     app,
@@ -18,19 +23,41 @@ widget_ids!(struct Ids {
 pub fn run() {
     let mut event_system = systems::EventSystem::new();
 
+    let settings = SettingsManager::open_default()
+        .map_err(|e| log::error!("Could not open settings, they will not be saved! {}", e))
+        .ok();
+    let mut view_settings = settings
+        .as_ref()
+        .map(ViewSettings::load)
+        .unwrap_or_default();
+    let slideshow_settings = settings
+        .as_ref()
+        .map(SlideshowSettings::load)
+        .unwrap_or_default();
+    let mut session = settings.as_ref().map(Session::load).unwrap_or_default();
+
+    let (window_width, window_height) = session.window.map_or(
+        (INITIAL_WINDOW_WIDTH as f64, INITIAL_WINDOW_HEIGHT as f64),
+        |g| (g.w, g.h),
+    );
+
     let mut events_loop = glium::glutin::EventsLoop::new();
     let window = glium::glutin::WindowBuilder::new()
         .with_title("Aspect")
-        .with_dimensions((INITIAL_WINDOW_WIDTH, INITIAL_WINDOW_HEIGHT).into());
+        .with_dimensions((window_width, window_height).into());
     let context = glium::glutin::ContextBuilder::new()
         .with_vsync(true)
         .with_multisampling(4);
     let display = glium::Display::new(window, context, &events_loop).unwrap();
     let display = GliumDisplayWinitWrapper(display);
+    if let Some(geometry) = session.window {
+        display
+            .0
+            .gl_window()
+            .set_position((geometry.x, geometry.y).into());
+    }
 
-    let mut ui =
-        conrod_core::UiBuilder::new([INITIAL_WINDOW_WIDTH as f64, INITIAL_WINDOW_HEIGHT as f64])
-            .build();
+    let mut ui = conrod_core::UiBuilder::new([window_width, window_height]).build();
     ui.fonts.insert(
         conrod_core::text::FontCollection::from_bytes(ttf_noto_sans::REGULAR)
             .unwrap()
@@ -41,18 +68,33 @@ pub fn run() {
 
     let mut renderer = conrod_glium::Renderer::new(&display.0).unwrap();
     let mut image_system = systems::ImageSystem::new(&display.0);
-    let mut file_list = crate::data::FileList::from_environment();
-    if let Some(file_list) = &file_list {
+    let mut file_list = FileList::from_environment().or_else(|| session.restore_files());
+    if let (Some(file_list), Some(settings)) = (&mut file_list, &settings) {
+        file_list.add_global_filters(settings);
+    }
+    if let Some(file_list) = &mut file_list {
         if let Some(file) = file_list.current() {
             event_system.push(e::AppEvent::Load(file.clone()));
         }
+        if let Some(companion) = file_list.companion(&view_settings) {
+            event_system.push(e::AppEvent::LoadCompanion(Some(companion.clone())));
+        }
+    }
+    if session.is_fullscreen {
+        event_system.push(e::Window::Fullscreen(true).into());
+    }
+    if session.is_overlay_visible {
+        event_system.push(e::Window::Overlay(true).into());
     }
 
     let resources = Resources::load(&mut image_system).unwrap();
 
     let ids = Ids::new(ui.widget_id_generator());
 
+    let mut window_system = systems::WindowSystem::new();
+    let mut slideshow = systems::SlideshowSystem::new(slideshow_settings);
     let mut event_loop = EventLoop::new();
+    let mut closing_frames = None;
     'main: loop {
         event_system.update();
 
@@ -64,7 +106,17 @@ pub fn run() {
 
             match event {
                 glium::glutin::Event::WindowEvent { event, .. } => match event {
-                    glium::glutin::WindowEvent::CloseRequested => break 'main,
+                    glium::glutin::WindowEvent::CloseRequested if closing_frames.is_none() => {
+                        event_system.push(e::Window::Close.into());
+                        closing_frames = Some(CLOSING_FRAMES);
+                    }
+                    glium::glutin::WindowEvent::CursorMoved { .. }
+                    | glium::glutin::WindowEvent::MouseInput { .. }
+                    | glium::glutin::WindowEvent::MouseWheel { .. }
+                    | glium::glutin::WindowEvent::KeyboardInput { .. } => {
+                        window_system.activity();
+                        slideshow.activity();
+                    }
                     _ => (),
                 },
                 _ => (),
@@ -72,19 +124,38 @@ pub fn run() {
         }
 
         display.update(&event_system);
-        image_system.update(&mut event_system).log_err();
+        window_system.update(&display.0, &mut event_system);
+        image_system
+            .update(&mut event_system, &view_settings)
+            .log_err();
+        view_settings.update(&event_system, settings.as_ref());
         if let Some(files) = &mut file_list {
-            files.update(&mut event_system);
+            files.update(&mut event_system, &view_settings, settings.as_ref());
         }
+        slideshow.update(
+            &mut event_system,
+            file_list.as_ref(),
+            &view_settings,
+            settings.as_ref(),
+        );
+        session.update(&event_system);
 
         {
             use conrod_core::{Positionable, Sizeable};
             let ui = &mut ui.set_widgets();
-            App::new(&mut event_system, &resources, &file_list)
-                .parent(ui.window)
-                .wh_of(ui.window)
-                .top_left()
-                .set(ids.app, ui);
+            App::new(
+                &mut event_system,
+                &resources,
+                &file_list,
+                image_system.thumbnails(),
+                image_system.pages(),
+                &view_settings,
+                &slideshow,
+            )
+            .parent(ui.window)
+            .wh_of(ui.window)
+            .top_left()
+            .set(ids.app, ui);
         }
 
         if let Some(primitives) = ui.draw_if_changed() {
@@ -95,5 +166,30 @@ pub fn run() {
             renderer.draw(&display.0, &mut target, image_map).unwrap();
             target.finish().unwrap();
         }
+
+        match &mut closing_frames {
+            Some(0) => break 'main,
+            Some(frames) => *frames -= 1,
+            None => (),
+        }
+    }
+
+    if let Some(settings) = settings {
+        session.window =
+            window_system
+                .geometry(&display.0)
+                .map(|(position, size)| WindowGeometry {
+                    x: position.x,
+                    y: position.y,
+                    w: size.width,
+                    h: size.height,
+                });
+        session.is_fullscreen = window_system.is_fullscreen();
+        if let Some(files) = &file_list {
+            session.set_files(files);
+        }
+        session.save(&settings).log_err();
+
+        settings.close().log_err();
     }
 }